@@ -0,0 +1,83 @@
+//! Throughput of the band chain at 96 kHz, run with
+//! `cargo bench --features bench`.
+
+use std::{hint::black_box, time::Instant};
+
+use freeq::bench::{BandChain, Precision};
+
+const SAMPLE_RATE: f32 = 96000.0;
+const BLOCK_SIZE: usize = 512;
+const SECONDS: usize = 20;
+
+/// How the chain runs over a block.
+#[derive(Clone, Copy)]
+enum Path {
+    Block,
+    /// The per sample path the block processing replaced.
+    PerSample,
+}
+
+/// Time ten bands, the first `active` of them boosting by 6 dB.
+fn bench(name: &str, path: Path, active: usize, precision: Precision) {
+    let mut chain = BandChain::new(active, precision, SAMPLE_RATE);
+
+    let mut frames = vec![[0.0; 2]; BLOCK_SIZE];
+    let mut seed = 0x2545_f491_u32;
+
+    let blocks = SECONDS * SAMPLE_RATE as usize / BLOCK_SIZE;
+    let mut elapsed = 0.0;
+
+    for _ in 0..blocks {
+        for frame in frames.iter_mut() {
+            for sample in frame.iter_mut() {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+
+                *sample = seed as f32 / u32::MAX as f32 - 0.5;
+            }
+        }
+
+        let start = Instant::now();
+        match path {
+            Path::Block => chain.process(black_box(&mut frames)),
+            Path::PerSample => chain.process_per_sample(black_box(&mut frames)),
+        }
+        elapsed += start.elapsed().as_secs_f64();
+
+        black_box(&frames);
+    }
+
+    let frames = (blocks * BLOCK_SIZE) as f64;
+
+    println!(
+        "{:<28} {:>8.2} ns/frame {:>10.1}x realtime",
+        name,
+        elapsed * 1.0e9 / frames,
+        SECONDS as f64 / elapsed,
+    );
+}
+
+fn main() {
+    bench(
+        "baseline, 10 active bands",
+        Path::PerSample,
+        10,
+        Precision::Single,
+    );
+    bench(
+        "baseline, 10 bands at unity",
+        Path::PerSample,
+        0,
+        Precision::Single,
+    );
+    bench("10 active bands", Path::Block, 10, Precision::Single);
+    bench(
+        "10 active bands, 64-bit",
+        Path::Block,
+        10,
+        Precision::Double,
+    );
+    bench("5 active, 5 at unity", Path::Block, 5, Precision::Single);
+    bench("10 bands at unity", Path::Block, 0, Precision::Single);
+}
@@ -0,0 +1,1663 @@
+use std::{
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use num::Complex;
+use ori_vst::prelude::*;
+use realfft::{RealFftPlanner, RealToComplex};
+
+use crate::{
+    lock_free::{ring, triple_buffer, Consumer, Producer, Reader, Writer},
+    matching::MatchEq,
+};
+
+/// A spectrum analyzer running overlapping windowed FFTs over a ring of the
+/// latest samples, scaled so a full scale sine reads 1.
+pub struct Analyzer {
+    fft: Arc<dyn RealToComplex<f32>>,
+    window: Vec<f32>,
+    window_kind: Window,
+    /// Makes up for the gain of the window and the bins being one sided.
+    scale: f32,
+    prev_input: f32,
+    prev_output: f32,
+    ring: Vec<f32>,
+    /// The windowed copy of the ring the FFT runs over.
+    frame: Vec<f32>,
+    complex: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    /// Magnitudes of the latest frame.
+    magnitudes: Vec<f32>,
+    /// Magnitudes averaged over the recent frames.
+    spectrum: Vec<f32>,
+    current: usize,
+    /// Samples between frames.
+    hop: usize,
+    countdown: usize,
+    /// How much of the average is kept per frame.
+    decay: f32,
+}
+
+impl Analyzer {
+    /// An analyzer with a Hann window, half overlapping frames and no
+    /// averaging.
+    pub fn new(size: usize) -> Self {
+        let window = Window::Hann.build(size);
+
+        Self {
+            fft: RealFftPlanner::new().plan_fft_forward(size),
+            scale: Self::scale(&window),
+            window,
+            window_kind: Window::Hann,
+            prev_input: 0.0,
+            prev_output: 0.0,
+            ring: vec![0.0; size],
+            frame: vec![0.0; size],
+            complex: vec![Complex::new(0.0, 0.0); size / 2 + 1],
+            scratch: vec![Complex::new(0.0, 0.0); size / 2 + 1],
+            magnitudes: vec![0.0; size / 2 + 1],
+            spectrum: vec![0.0; size / 2 + 1],
+            current: 0,
+            hop: size / 2,
+            countdown: size / 2,
+            decay: 0.0,
+        }
+    }
+
+    /// Apply the settings, the buffers are only reallocated if `size`
+    /// changes. `overlap` is the fraction of a frame shared with the next.
+    pub fn configure(
+        &mut self,
+        size: usize,
+        overlap: f32,
+        window: Window,
+        decay_ms: f32,
+        sample_rate: f32,
+    ) {
+        if size != self.size() {
+            *self = Self::new(size);
+        }
+
+        if window.id() != self.window_kind.id() {
+            self.window = window.build(self.size());
+            self.window_kind = window;
+            self.scale = Self::scale(&self.window);
+        }
+
+        let hop = (1.0 - overlap) * self.size() as f32;
+        self.hop = (hop.round() as usize).clamp(1, self.size());
+        self.countdown = self.countdown.min(self.hop);
+
+        // the time constant is per frame, so it depends on the hop
+        self.decay = match decay_ms > 0.0 {
+            true => f32::exp(-(self.hop as f32) / (decay_ms / 1000.0 * sample_rate)),
+            false => 0.0,
+        };
+    }
+
+    /// The coherent gain of `window` is its mean, and a sine splits its
+    /// power between the positive and negative bins.
+    fn scale(window: &[f32]) -> f32 {
+        2.0 / window.iter().sum::<f32>()
+    }
+
+    pub fn size(&self) -> usize {
+        self.ring.len()
+    }
+
+    pub fn magnitudes(&self) -> &[f32] {
+        &self.magnitudes
+    }
+
+    pub fn spectrum(&self) -> &[f32] {
+        &self.spectrum
+    }
+
+    /// The averaged level at `freq`, with the power interpolated between
+    /// the bins around it.
+    fn level_at(&self, freq: f32, sample_rate: f32) -> f32 {
+        let position = freq * self.size() as f32 / sample_rate;
+        let bin = usize::min(position as usize, self.spectrum.len() - 2);
+        let frac = (position - bin as f32).clamp(0.0, 1.0);
+
+        let below = self.spectrum[bin] * self.spectrum[bin];
+        let above = self.spectrum[bin + 1] * self.spectrum[bin + 1];
+
+        f32::sqrt(below + frac * (above - below))
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+        self.ring.fill(0.0);
+        self.magnitudes.fill(0.0);
+        self.spectrum.fill(0.0);
+        self.current = 0;
+        self.countdown = self.hop;
+    }
+
+    /// Push a sample, returns `true` if a new frame was analyzed.
+    pub fn push(&mut self, sample: f32) -> bool {
+        // apply high-pass filter to remove DC offset
+        let high_pass = 0.999 * (self.prev_output + sample - self.prev_input);
+        self.prev_input = sample;
+        self.prev_output = high_pass;
+
+        self.ring[self.current] = high_pass;
+        self.current = (self.current + 1) % self.ring.len();
+
+        self.countdown -= 1;
+
+        if self.countdown > 0 {
+            return false;
+        }
+
+        self.countdown = self.hop;
+        self.compute_fft();
+
+        true
+    }
+
+    fn compute_fft(&mut self) {
+        // the oldest sample is at the write position
+        let (newer, older) = self.ring.split_at(self.current);
+        let samples = older.iter().chain(newer);
+
+        for ((frame, sample), window) in self.frame.iter_mut().zip(samples).zip(&self.window) {
+            *frame = sample * window;
+        }
+
+        self.fft
+            .process_with_scratch(&mut self.frame, &mut self.complex, &mut self.scratch)
+            .unwrap();
+
+        let bins = self.spectrum.iter_mut().zip(self.magnitudes.iter_mut());
+        let last = self.complex.len() - 1;
+
+        for (i, ((spectrum, magnitude), complex)) in bins.zip(self.complex.iter()).enumerate() {
+            // dc and nyquist have no negative twin
+            let scale = match i == 0 || i == last {
+                true => self.scale / 2.0,
+                false => self.scale,
+            };
+
+            *magnitude = complex.norm() * scale;
+            *magnitude = magnitude.max(1.0e-6);
+
+            *spectrum = *magnitude + self.decay * (*spectrum - *magnitude);
+        }
+    }
+}
+
+/// Analyzers of decreasing size, each covering the frequencies it resolves
+/// well enough, so the lows get narrow bins and the highs a fast response.
+/// Each larger analyzer fades out over `FADE_OCTAVES` above its crossover.
+///
+/// Every analyzer is scaled for the coherent gain of its window, so a sine
+/// reads its level on both sides of a crossover. Broadband noise reads 6 dB
+/// higher in the highs and 6 dB lower in the lows, where the bins are four
+/// times and a fourth as wide.
+pub struct MultiAnalyzer {
+    /// Largest first.
+    analyzers: Vec<Analyzer>,
+    sample_rate: f32,
+}
+
+impl MultiAnalyzer {
+    /// Bins per octave an analyzer needs before it takes over from a larger
+    /// one.
+    const BINS_PER_OCTAVE: f32 = 12.0;
+
+    const MIN_SIZE: usize = 256;
+
+    /// Width of the crossfade above each crossover.
+    const FADE_OCTAVES: f32 = 1.0;
+
+    pub fn new(size: usize) -> Self {
+        Self {
+            analyzers: vec![Analyzer::new(size)],
+            sample_rate: 44100.0,
+        }
+    }
+
+    /// `size` is used for the whole spectrum with `Resolution::Single`, and
+    /// for the mids with `Resolution::Multi`, with four times that for the
+    /// lows and a fourth for the highs.
+    pub fn configure(
+        &mut self,
+        size: FftSize,
+        resolution: Resolution,
+        overlap: f32,
+        window: Window,
+        decay_ms: f32,
+        sample_rate: f32,
+    ) {
+        let size = size.size();
+
+        let mut sizes = match resolution {
+            Resolution::Single => vec![size],
+            Resolution::Multi => vec![
+                usize::min(size * 4, FftSize::K32.size()),
+                size,
+                usize::max(size / 4, Self::MIN_SIZE),
+            ],
+        };
+
+        sizes.dedup();
+
+        // resized to fit when configured
+        let new = || Analyzer::new(Self::MIN_SIZE);
+        self.analyzers.resize_with(sizes.len(), new);
+
+        for (analyzer, size) in self.analyzers.iter_mut().zip(sizes) {
+            analyzer.configure(size, overlap, window, decay_ms, sample_rate);
+        }
+
+        self.sample_rate = sample_rate;
+    }
+
+    pub fn reset(&mut self) {
+        for analyzer in self.analyzers.iter_mut() {
+            analyzer.reset();
+        }
+    }
+
+    /// Push a sample, returns `true` if a new frame was analyzed.
+    pub fn push(&mut self, sample: f32) -> bool {
+        let mut analyzed = false;
+
+        for analyzer in self.analyzers.iter_mut() {
+            analyzed |= analyzer.push(sample);
+        }
+
+        analyzed
+    }
+
+    /// The frequency and level of every bin shown, in ascending order.
+    pub fn points(&self) -> impl Iterator<Item = (f32, f32)> + '_ {
+        let sample_rate = self.sample_rate;
+        let fade = f32::powf(2.0, Self::FADE_OCTAVES);
+
+        // where `analyzer` starts fading in over the larger one before it
+        let crossover = move |analyzer: Option<&Analyzer>| match analyzer {
+            Some(analyzer) => Self::BINS_PER_OCTAVE * sample_rate / analyzer.size() as f32,
+            None => f32::INFINITY,
+        };
+
+        self.analyzers
+            .iter()
+            .enumerate()
+            .flat_map(move |(i, analyzer)| {
+                let next = self.analyzers.get(i + 1);
+
+                let low = match i {
+                    0 => 0.0,
+                    _ => crossover(Some(analyzer)) * fade,
+                };
+                let start = crossover(next);
+                let high = start * fade;
+
+                let bin_width = sample_rate / analyzer.size() as f32;
+                let bins = analyzer.spectrum().iter().enumerate();
+
+                bins.map(move |(bin, &level)| {
+                    let freq = bin as f32 * bin_width;
+
+                    // blend in the power of the next analyzer over the fade
+                    let level = match next {
+                        Some(next) if freq >= start => {
+                            let t = f32::log2(freq / start) / Self::FADE_OCTAVES;
+                            let other = next.level_at(freq, sample_rate);
+
+                            f32::sqrt((1.0 - t) * level * level + t * other * other)
+                        }
+                        _ => level,
+                    };
+
+                    (freq, level)
+                })
+                .filter(move |(freq, _)| (low..high).contains(freq))
+            })
+    }
+}
+
+/// An analyzer per trace of `StereoMode`.
+pub struct StereoAnalyzer {
+    analyzers: [MultiAnalyzer; 2],
+    count: usize,
+}
+
+impl StereoAnalyzer {
+    pub fn new(size: usize) -> Self {
+        Self {
+            analyzers: [MultiAnalyzer::new(size), MultiAnalyzer::new(size)],
+            count: 1,
+        }
+    }
+
+    pub fn configure(&mut self, settings: &AnalyzerSettings, sample_rate: f32) {
+        for analyzer in self.analyzers.iter_mut() {
+            analyzer.configure(
+                settings.size,
+                settings.resolution,
+                settings.overlap,
+                settings.window,
+                settings.decay_ms,
+                sample_rate,
+            );
+        }
+    }
+
+    /// The analyzers of the traces pushed last.
+    pub fn traces(&self) -> &[MultiAnalyzer] {
+        &self.analyzers[..self.count]
+    }
+
+    /// Push a frame, returns `true` if a new frame was analyzed.
+    pub fn push(&mut self, frame: &[f32; 2], channels: usize, mode: StereoMode) -> bool {
+        let (signals, count) = mode.split(frame, channels);
+
+        // a trace coming back starts over instead of showing stale data
+        for analyzer in self.analyzers.iter_mut().take(count).skip(self.count) {
+            analyzer.reset();
+        }
+
+        self.count = count;
+
+        let mut analyzed = false;
+
+        for (analyzer, signal) in self.analyzers.iter_mut().zip(&signals[..count]) {
+            analyzed |= analyzer.push(*signal);
+        }
+
+        analyzed
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AnalyzerSettings {
+    pub size: FftSize,
+    pub resolution: Resolution,
+    /// Fraction of a frame shared with the next.
+    pub overlap: f32,
+    pub window: Window,
+    pub decay_ms: f32,
+    pub smoothing: Smoothing,
+    pub stereo: StereoMode,
+    pub traces: Traces,
+    /// Time a peak is held before it falls.
+    pub peak_hold_ms: f32,
+    /// Rate a peak falls at in dB per second.
+    pub peak_fall: f32,
+}
+
+/// The settings and the signal layout, stored by the audio thread for the
+/// worker.
+#[derive(Default)]
+struct SharedSettings {
+    size: AtomicU32,
+    resolution: AtomicU32,
+    overlap: AtomicU32,
+    window: AtomicU32,
+    decay_ms: AtomicU32,
+    smoothing: AtomicU32,
+    stereo: AtomicU32,
+    traces: AtomicU32,
+    peak_hold_ms: AtomicU32,
+    peak_fall: AtomicU32,
+    sample_rate: AtomicU32,
+    channels: AtomicU32,
+    sidechain: AtomicBool,
+    /// Set by the ui to reset the max.
+    clear_max: AtomicBool,
+}
+
+/// The spectra of the traces, published by the worker.
+#[derive(Clone, Default)]
+pub struct Spectra {
+    /// Frequencies of the points of every spectrum.
+    pub freqs: Vec<f32>,
+    pub pre: Vec<Vec<f32>>,
+    pub post: Vec<Vec<f32>>,
+    /// Peak hold of the traces the ui fills, the post traces if they're
+    /// shown and the pre traces otherwise.
+    pub peak: Vec<Vec<f32>>,
+    /// The highest levels of the same traces since the max was cleared.
+    pub max: Vec<Vec<f32>>,
+}
+
+/// The audio thread side of the analyzer, it only copies frames into rings
+/// and never runs an FFT.
+pub struct AnalyzerFeed {
+    settings: Arc<SharedSettings>,
+    input: Producer<[f32; 2]>,
+    output: Producer<[f32; 2]>,
+    keys: Producer<[f32; 2]>,
+}
+
+impl AnalyzerFeed {
+    pub fn set_settings(
+        &mut self,
+        settings: AnalyzerSettings,
+        sample_rate: f32,
+        channels: usize,
+        sidechain: bool,
+    ) {
+        let shared = &self.settings;
+
+        shared.size.store(settings.size.id(), Ordering::Relaxed);
+        shared
+            .resolution
+            .store(settings.resolution.id(), Ordering::Relaxed);
+        shared
+            .overlap
+            .store(settings.overlap.to_bits(), Ordering::Relaxed);
+        shared.window.store(settings.window.id(), Ordering::Relaxed);
+        shared
+            .decay_ms
+            .store(settings.decay_ms.to_bits(), Ordering::Relaxed);
+        shared
+            .smoothing
+            .store(settings.smoothing.id(), Ordering::Relaxed);
+        shared.stereo.store(settings.stereo.id(), Ordering::Relaxed);
+        shared.traces.store(settings.traces.id(), Ordering::Relaxed);
+        shared
+            .peak_hold_ms
+            .store(settings.peak_hold_ms.to_bits(), Ordering::Relaxed);
+        shared
+            .peak_fall
+            .store(settings.peak_fall.to_bits(), Ordering::Relaxed);
+        shared
+            .sample_rate
+            .store(sample_rate.to_bits(), Ordering::Relaxed);
+        shared.channels.store(channels as u32, Ordering::Relaxed);
+        shared.sidechain.store(sidechain, Ordering::Relaxed);
+    }
+
+    /// Start the max over from the current spectrum.
+    pub fn clear_max(&self) {
+        self.settings.clear_max.store(true, Ordering::Relaxed);
+    }
+
+    /// Push the input of the band chain, frames that don't fit are dropped.
+    pub fn push_input(&mut self, frames: &[[f32; 2]]) {
+        self.input.push(frames);
+    }
+
+    /// Push the output of the band chain.
+    pub fn push_output(&mut self, frames: &[[f32; 2]]) {
+        self.output.push(frames);
+    }
+
+    /// Push the sidechain input.
+    pub fn push_keys(&mut self, keys: &[[f32; 2]]) {
+        self.keys.push(keys);
+    }
+}
+
+/// Stops and joins the worker when dropped.
+pub struct AnalyzerThread {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl Drop for AnalyzerThread {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Start the analyzer worker, which also captures the spectra of `matching`.
+pub fn spawn(matching: Arc<Mutex<MatchEq>>) -> (AnalyzerFeed, Reader<Spectra>, AnalyzerThread) {
+    let settings = Arc::new(SharedSettings::default());
+    let running = Arc::new(AtomicBool::new(true));
+
+    let (input, input_consumer) = ring(Worker::RING_CAPACITY);
+    let (output, output_consumer) = ring(Worker::RING_CAPACITY);
+    let (keys, keys_consumer) = ring(Worker::RING_CAPACITY);
+    let (spectra, reader) = triple_buffer(Spectra::default());
+
+    let size = FftSize::K4.size();
+
+    let mut worker = Worker {
+        settings: settings.clone(),
+        running: running.clone(),
+        input: input_consumer,
+        output: output_consumer,
+        keys: keys_consumer,
+        pre: StereoAnalyzer::new(size),
+        post: StereoAnalyzer::new(size),
+        matching,
+        spectra,
+        frames: vec![[0.0; 2]; Worker::CHUNK],
+        sums: Vec::new(),
+        holds: Holds::default(),
+    };
+
+    let handle = thread::Builder::new()
+        .name(String::from("freeq analyzer"))
+        .spawn(move || worker.run())
+        .expect("failed to spawn the analyzer thread");
+
+    let feed = AnalyzerFeed {
+        settings,
+        input,
+        output,
+        keys,
+    };
+
+    let thread = AnalyzerThread {
+        running,
+        handle: Some(handle),
+    };
+
+    (feed, reader, thread)
+}
+
+struct Worker {
+    settings: Arc<SharedSettings>,
+    running: Arc<AtomicBool>,
+    input: Consumer<[f32; 2]>,
+    output: Consumer<[f32; 2]>,
+    keys: Consumer<[f32; 2]>,
+    pre: StereoAnalyzer,
+    post: StereoAnalyzer,
+    matching: Arc<Mutex<MatchEq>>,
+    spectra: Writer<Spectra>,
+    frames: Vec<[f32; 2]>,
+    /// Running sums of the power for smoothing.
+    sums: Vec<f64>,
+    holds: Holds,
+}
+
+impl Worker {
+    /// Frames the rings hold, about 170 ms at 192 kHz.
+    const RING_CAPACITY: usize = 1 << 15;
+
+    /// Frames popped from a ring at a time.
+    const CHUNK: usize = 1024;
+
+    /// Time between draining the rings, well within what they hold.
+    const INTERVAL: Duration = Duration::from_millis(5);
+
+    fn run(&mut self) {
+        while self.running.load(Ordering::Relaxed) {
+            let settings = self.load_settings();
+            let analyzed = self.analyze(&settings);
+
+            let cleared = self.settings.clear_max.swap(false, Ordering::Relaxed);
+
+            if cleared {
+                self.holds.clear_max();
+            }
+
+            if analyzed || cleared {
+                self.publish(&settings);
+            }
+
+            thread::sleep(Self::INTERVAL);
+        }
+    }
+
+    fn load_settings(&self) -> AnalyzerSettings {
+        let shared = &self.settings;
+
+        AnalyzerSettings {
+            size: FftSize::from_id(shared.size.load(Ordering::Relaxed)).unwrap_or_default(),
+            resolution: Resolution::from_id(shared.resolution.load(Ordering::Relaxed))
+                .unwrap_or_default(),
+            overlap: f32::from_bits(shared.overlap.load(Ordering::Relaxed)),
+            window: Window::from_id(shared.window.load(Ordering::Relaxed)).unwrap_or_default(),
+            decay_ms: f32::from_bits(shared.decay_ms.load(Ordering::Relaxed)),
+            smoothing: Smoothing::from_id(shared.smoothing.load(Ordering::Relaxed))
+                .unwrap_or_default(),
+            stereo: StereoMode::from_id(shared.stereo.load(Ordering::Relaxed)).unwrap_or_default(),
+            traces: Traces::from_id(shared.traces.load(Ordering::Relaxed)).unwrap_or_default(),
+            peak_hold_ms: f32::from_bits(shared.peak_hold_ms.load(Ordering::Relaxed)),
+            peak_fall: f32::from_bits(shared.peak_fall.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Drain the rings, returns `true` if a new frame was analyzed.
+    fn analyze(&mut self, settings: &AnalyzerSettings) -> bool {
+        let shared = &self.settings;
+
+        let sample_rate = f32::from_bits(shared.sample_rate.load(Ordering::Relaxed));
+        let channels = shared.channels.load(Ordering::Relaxed).max(1) as usize;
+        let sidechain = shared.sidechain.load(Ordering::Relaxed);
+
+        for analyzer in [&mut self.pre, &mut self.post] {
+            analyzer.configure(settings, sample_rate);
+        }
+
+        let mut analyzed = false;
+
+        loop {
+            let count = self.input.pop(&mut self.frames);
+
+            if count == 0 {
+                break;
+            }
+
+            let frames = &self.frames[..count];
+
+            if settings.traces.shows_pre() {
+                for frame in frames {
+                    analyzed |= self.pre.push(frame, channels, settings.stereo);
+                }
+            }
+
+            let mut matching = self.matching.lock().unwrap();
+            matching.capture(frames, channels, sidechain);
+        }
+
+        loop {
+            let count = self.output.pop(&mut self.frames);
+
+            if count == 0 {
+                break;
+            }
+
+            if settings.traces.shows_post() {
+                for frame in &self.frames[..count] {
+                    analyzed |= self.post.push(frame, channels, settings.stereo);
+                }
+            }
+        }
+
+        loop {
+            let count = self.keys.pop(&mut self.frames);
+
+            if count == 0 {
+                break;
+            }
+
+            let mut matching = self.matching.lock().unwrap();
+            matching.capture_keys(&self.frames[..count]);
+        }
+
+        analyzed
+    }
+
+    fn publish(&mut self, settings: &AnalyzerSettings) {
+        let Spectra {
+            freqs,
+            pre,
+            post,
+            peak,
+            max,
+        } = self.spectra.slot();
+
+        // every trace has the same points
+        let points = self.pre.traces()[0].points();
+        freqs.clear();
+        freqs.extend(points.map(|(freq, _)| freq));
+
+        let traces = [(&mut *pre, &self.pre), (&mut *post, &self.post)];
+
+        for (spectra, analyzer) in traces {
+            let analyzers = analyzer.traces();
+            spectra.resize_with(analyzers.len(), Vec::new);
+
+            for (spectrum, analyzer) in spectra.iter_mut().zip(analyzers) {
+                spectrum.clear();
+                spectrum.extend(analyzer.points().map(|(_, level)| level));
+
+                if let Some(octaves) = settings.smoothing.octaves() {
+                    smooth(freqs, spectrum, octaves, &mut self.sums);
+                }
+            }
+        }
+
+        let filled = match settings.traces.shows_post() {
+            true => post,
+            false => pre,
+        };
+
+        self.holds
+            .update(filled, settings.peak_hold_ms, settings.peak_fall);
+
+        peak.clone_from(&self.holds.peak);
+        max.clone_from(&self.holds.max);
+
+        self.spectra.publish();
+    }
+}
+
+/// The peak hold and the max of the traces, kept by the worker since the
+/// published slots are reused.
+#[derive(Default)]
+struct Holds {
+    peak: Vec<Vec<f32>>,
+    /// Seconds left before each peak starts falling.
+    held: Vec<Vec<f32>>,
+    max: Vec<Vec<f32>>,
+    last_update: Option<Instant>,
+}
+
+impl Holds {
+    fn clear_max(&mut self) {
+        self.max.clear();
+    }
+
+    fn update(&mut self, traces: &[Vec<f32>], hold_ms: f32, fall: f32) {
+        let now = Instant::now();
+        let elapsed = match self.last_update {
+            Some(last_update) => (now - last_update).as_secs_f32(),
+            None => 0.0,
+        };
+
+        self.last_update = Some(now);
+
+        let hold = hold_ms / 1000.0;
+        let fall = f32::powf(10.0, -fall * elapsed / 20.0);
+
+        // the traces changed, so start over from them
+        let fits = |holds: &[Vec<f32>]| {
+            holds.len() == traces.len() && holds.iter().zip(traces).all(|(a, b)| a.len() == b.len())
+        };
+
+        if !fits(&self.peak) {
+            self.peak = traces.to_vec();
+            self.held = traces
+                .iter()
+                .map(|levels| vec![hold; levels.len()])
+                .collect();
+        }
+
+        if !fits(&self.max) {
+            self.max = traces.to_vec();
+        }
+
+        let peaks = self.peak.iter_mut().zip(self.held.iter_mut());
+
+        for ((peaks, held), levels) in peaks.zip(traces) {
+            for ((peak, held), &level) in peaks.iter_mut().zip(held).zip(levels) {
+                match *held > 0.0 {
+                    true => *held -= elapsed,
+                    false => *peak *= fall,
+                }
+
+                // caught up with, so held again
+                if level >= *peak {
+                    *peak = level;
+                    *held = hold;
+                }
+            }
+        }
+
+        for (maxima, levels) in self.max.iter_mut().zip(traces) {
+            for (max, &level) in maxima.iter_mut().zip(levels) {
+                *max = f32::max(*max, level);
+            }
+        }
+    }
+}
+
+/// Average the power of `levels` at `freqs` over `octaves` around every
+/// point, with `sums` as scratch.
+fn smooth(freqs: &[f32], levels: &mut [f32], octaves: f32, sums: &mut Vec<f64>) {
+    sums.clear();
+    sums.push(0.0);
+
+    let mut sum = 0.0;
+
+    for &level in levels.iter() {
+        sum += level as f64 * level as f64;
+        sums.push(sum);
+    }
+
+    let half_width = f32::powf(2.0, octaves / 2.0);
+
+    // the points are ascending, so the bounds only ever move up
+    let mut low = 0;
+    let mut high = 0;
+
+    for (&freq, level) in freqs.iter().zip(levels.iter_mut()) {
+        while freqs[low] < freq / half_width {
+            low += 1;
+        }
+
+        while high < freqs.len() && freqs[high] <= freq * half_width {
+            high += 1;
+        }
+
+        let power = (sums[high] - sums[low]) / (high - low) as f64;
+        *level = power.sqrt() as f32;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum FftSize {
+    K1,
+    K2,
+    #[default]
+    K4,
+    K8,
+    K16,
+    K32,
+}
+
+impl FftSize {
+    pub const MAX_ID: u32 = 5;
+
+    pub fn size(&self) -> usize {
+        1024 << self.id()
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            FftSize::K1 => 0,
+            FftSize::K2 => 1,
+            FftSize::K4 => 2,
+            FftSize::K8 => 3,
+            FftSize::K16 => 4,
+            FftSize::K32 => 5,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<FftSize> {
+        match id {
+            0 => Some(FftSize::K1),
+            1 => Some(FftSize::K2),
+            2 => Some(FftSize::K4),
+            3 => Some(FftSize::K8),
+            4 => Some(FftSize::K16),
+            5 => Some(FftSize::K32),
+            _ => None,
+        }
+    }
+
+    pub fn prev(&self) -> FftSize {
+        let id = self.id();
+        let prev_id = (id + Self::MAX_ID) % (Self::MAX_ID + 1);
+        FftSize::from_id(prev_id).unwrap()
+    }
+
+    pub fn next(&self) -> FftSize {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        FftSize::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for FftSize {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = FftSize::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::K4.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match FftSize::from_id(plain.round() as u32) {
+            Some(size) => format!("{}k", size.size() / 1024),
+            None => {
+                println!("FftSize::to_string: invalid id: {}", plain);
+                String::from("4k")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "1k" => FftSize::K1.get(),
+            "2k" => FftSize::K2.get(),
+            "4k" => FftSize::K4.get(),
+            "8k" => FftSize::K8.get(),
+            "16k" => FftSize::K16.get(),
+            "32k" => FftSize::K32.get(),
+            _ => FftSize::K4.get(),
+        }
+    }
+}
+
+/// Whether the analyzer uses one FFT size or several.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Resolution {
+    #[default]
+    Single,
+    Multi,
+}
+
+impl Resolution {
+    pub const MAX_ID: u32 = 1;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Resolution::Single => "Single",
+            Resolution::Multi => "Multi",
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Resolution::Single => 0,
+            Resolution::Multi => 1,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Resolution> {
+        match id {
+            0 => Some(Resolution::Single),
+            1 => Some(Resolution::Multi),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Resolution {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Resolution::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Resolution {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Resolution::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Single.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Resolution::from_id(plain.round() as u32) {
+            Some(resolution) => String::from(resolution.abbreviation()),
+            None => {
+                println!("Resolution::to_string: invalid id: {}", plain);
+                String::from("Single")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Single" => Resolution::Single.get(),
+            "Multi" => Resolution::Multi.get(),
+            _ => Resolution::Single.get(),
+        }
+    }
+}
+
+/// The window the analyzer frames are shaped with.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Window {
+    Rectangular,
+    #[default]
+    Hann,
+    BlackmanHarris,
+    FlatTop,
+}
+
+impl Window {
+    pub const MAX_ID: u32 = 3;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Window::Rectangular => "Rect",
+            Window::Hann => "Hann",
+            Window::BlackmanHarris => "BH",
+            Window::FlatTop => "Flat",
+        }
+    }
+
+    /// Cosine series coefficients of the window.
+    fn coefficients(&self) -> &[f32] {
+        match self {
+            Window::Rectangular => &[1.0],
+            Window::Hann => &[0.5, 0.5],
+            Window::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+            Window::FlatTop => &[0.21557895, 0.41663158, 0.27726316, 0.08357895, 0.00694737],
+        }
+    }
+
+    pub fn build(&self, size: usize) -> Vec<f32> {
+        let coefficients = self.coefficients();
+
+        (0..size)
+            .map(|i| {
+                // periodic, so overlapping frames add up evenly
+                let x = 2.0 * PI * i as f32 / size as f32;
+
+                // the terms alternate in sign
+                let terms = coefficients.iter().enumerate().map(|(k, a)| {
+                    let sign = if k % 2 == 0 { 1.0 } else { -1.0 };
+                    sign * a * f32::cos(k as f32 * x)
+                });
+
+                terms.sum()
+            })
+            .collect()
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Window::Rectangular => 0,
+            Window::Hann => 1,
+            Window::BlackmanHarris => 2,
+            Window::FlatTop => 3,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Window> {
+        match id {
+            0 => Some(Window::Rectangular),
+            1 => Some(Window::Hann),
+            2 => Some(Window::BlackmanHarris),
+            3 => Some(Window::FlatTop),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Window {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Window::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Window {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Window::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Hann.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Window::from_id(plain.round() as u32) {
+            Some(Window::Rectangular) => String::from("Rectangular"),
+            Some(Window::Hann) => String::from("Hann"),
+            Some(Window::BlackmanHarris) => String::from("Blackman-Harris"),
+            Some(Window::FlatTop) => String::from("Flat Top"),
+            None => {
+                println!("Window::to_string: invalid id: {}", plain);
+                String::from("Hann")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Rectangular" => Window::Rectangular.get(),
+            "Hann" => Window::Hann.get(),
+            "Blackman-Harris" => Window::BlackmanHarris.get(),
+            "Flat Top" => Window::FlatTop.get(),
+            _ => Window::Hann.get(),
+        }
+    }
+}
+
+/// Width the analyzer spectra are smoothed over.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Smoothing {
+    #[default]
+    Off,
+    Octave24,
+    Octave12,
+    Octave6,
+    Octave3,
+}
+
+impl Smoothing {
+    pub const MAX_ID: u32 = 4;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Smoothing::Off => "Off",
+            Smoothing::Octave24 => "1/24",
+            Smoothing::Octave12 => "1/12",
+            Smoothing::Octave6 => "1/6",
+            Smoothing::Octave3 => "1/3",
+        }
+    }
+
+    /// Width in octaves, `None` if not smoothed.
+    pub fn octaves(&self) -> Option<f32> {
+        match self {
+            Smoothing::Off => None,
+            Smoothing::Octave24 => Some(1.0 / 24.0),
+            Smoothing::Octave12 => Some(1.0 / 12.0),
+            Smoothing::Octave6 => Some(1.0 / 6.0),
+            Smoothing::Octave3 => Some(1.0 / 3.0),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Smoothing::Off => 0,
+            Smoothing::Octave24 => 1,
+            Smoothing::Octave12 => 2,
+            Smoothing::Octave6 => 3,
+            Smoothing::Octave3 => 4,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Smoothing> {
+        match id {
+            0 => Some(Smoothing::Off),
+            1 => Some(Smoothing::Octave24),
+            2 => Some(Smoothing::Octave12),
+            3 => Some(Smoothing::Octave6),
+            4 => Some(Smoothing::Octave3),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Smoothing {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Smoothing::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Smoothing {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Smoothing::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Off.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Smoothing::from_id(plain.round() as u32) {
+            Some(Smoothing::Off) => String::from("Off"),
+            Some(smoothing) => format!("{} oct", smoothing.abbreviation()),
+            None => {
+                println!("Smoothing::to_string: invalid id: {}", plain);
+                String::from("Off")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Off" => Smoothing::Off.get(),
+            "1/24 oct" => Smoothing::Octave24.get(),
+            "1/12 oct" => Smoothing::Octave12.get(),
+            "1/6 oct" => Smoothing::Octave6.get(),
+            "1/3 oct" => Smoothing::Octave3.get(),
+            _ => Smoothing::Off.get(),
+        }
+    }
+}
+
+/// Slope the analyzer display is tilted by, around 1 kHz.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Tilt {
+    Flat,
+    #[default]
+    Db3,
+    Db4_5,
+    Db6,
+}
+
+impl Tilt {
+    pub const MAX_ID: u32 = 3;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Tilt::Flat => "0 dB",
+            Tilt::Db3 => "3 dB",
+            Tilt::Db4_5 => "4.5 dB",
+            Tilt::Db6 => "6 dB",
+        }
+    }
+
+    pub fn db_per_octave(&self) -> f32 {
+        match self {
+            Tilt::Flat => 0.0,
+            Tilt::Db3 => 3.0,
+            Tilt::Db4_5 => 4.5,
+            Tilt::Db6 => 6.0,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Tilt::Flat => 0,
+            Tilt::Db3 => 1,
+            Tilt::Db4_5 => 2,
+            Tilt::Db6 => 3,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Tilt> {
+        match id {
+            0 => Some(Tilt::Flat),
+            1 => Some(Tilt::Db3),
+            2 => Some(Tilt::Db4_5),
+            3 => Some(Tilt::Db6),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Tilt {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Tilt::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Tilt {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Tilt::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Db3.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Tilt::from_id(plain.round() as u32) {
+            Some(tilt) => format!("{}/oct", tilt.abbreviation()),
+            None => {
+                println!("Tilt::to_string: invalid id: {}", plain);
+                String::from("3 dB/oct")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "0 dB/oct" => Tilt::Flat.get(),
+            "3 dB/oct" => Tilt::Db3.get(),
+            "4.5 dB/oct" => Tilt::Db4_5.get(),
+            "6 dB/oct" => Tilt::Db6.get(),
+            _ => Tilt::Db3.get(),
+        }
+    }
+}
+
+/// Which spectra the analyzer shows, before or after the bands.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Traces {
+    #[default]
+    Post,
+    Pre,
+    Both,
+}
+
+impl Traces {
+    pub const MAX_ID: u32 = 2;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Traces::Post => "Post",
+            Traces::Pre => "Pre",
+            Traces::Both => "Both",
+        }
+    }
+
+    pub fn shows_pre(&self) -> bool {
+        matches!(self, Traces::Pre | Traces::Both)
+    }
+
+    pub fn shows_post(&self) -> bool {
+        matches!(self, Traces::Post | Traces::Both)
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Traces::Post => 0,
+            Traces::Pre => 1,
+            Traces::Both => 2,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Traces> {
+        match id {
+            0 => Some(Traces::Post),
+            1 => Some(Traces::Pre),
+            2 => Some(Traces::Both),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Traces {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Traces::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Traces {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Traces::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Post.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Traces::from_id(plain.round() as u32) {
+            Some(traces) => String::from(traces.abbreviation()),
+            None => {
+                println!("Traces::to_string: invalid id: {}", plain);
+                String::from("Post")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Post" => Traces::Post.get(),
+            "Pre" => Traces::Pre.get(),
+            "Both" => Traces::Both.get(),
+            _ => Traces::Post.get(),
+        }
+    }
+}
+
+/// How the analyzer treats the stereo signal.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum StereoMode {
+    #[default]
+    Sum,
+    LeftRight,
+    MidSide,
+}
+
+impl StereoMode {
+    pub const MAX_ID: u32 = 2;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            StereoMode::Sum => "L+R",
+            StereoMode::LeftRight => "L/R",
+            StereoMode::MidSide => "M/S",
+        }
+    }
+
+    /// The signals of the traces of `frame`, with their count.
+    pub fn split(&self, frame: &[f32; 2], channels: usize) -> ([f32; 2], usize) {
+        let [left, right] = *frame;
+
+        match (self, channels) {
+            (_, 1) => ([left, 0.0], 1),
+            (StereoMode::Sum, _) => ([(left + right) / 2.0, 0.0], 1),
+            (StereoMode::LeftRight, _) => ([left, right], 2),
+            (StereoMode::MidSide, _) => ([(left + right) / 2.0, (left - right) / 2.0], 2),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            StereoMode::Sum => 0,
+            StereoMode::LeftRight => 1,
+            StereoMode::MidSide => 2,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<StereoMode> {
+        match id {
+            0 => Some(StereoMode::Sum),
+            1 => Some(StereoMode::LeftRight),
+            2 => Some(StereoMode::MidSide),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> StereoMode {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        StereoMode::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for StereoMode {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = StereoMode::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Sum.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match StereoMode::from_id(plain.round() as u32) {
+            Some(StereoMode::Sum) => String::from("Sum"),
+            Some(StereoMode::LeftRight) => String::from("Left/Right"),
+            Some(StereoMode::MidSide) => String::from("Mid/Side"),
+            None => {
+                println!("StereoMode::to_string: invalid id: {}", plain);
+                String::from("Sum")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Sum" => StereoMode::Sum.get(),
+            "Left/Right" => StereoMode::LeftRight.get(),
+            "Mid/Side" => StereoMode::MidSide.get(),
+            _ => StereoMode::Sum.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sine_reads_its_level_across_the_crossovers() {
+        let sample_rate = 48000.0;
+        let amplitude = 0.5;
+
+        let mut analyzer = MultiAnalyzer::new(FftSize::K4.size());
+        analyzer.configure(
+            FftSize::K4,
+            Resolution::Multi,
+            0.5,
+            Window::FlatTop,
+            0.0,
+            sample_rate,
+        );
+
+        // the crossovers are at 141 Hz and 563 Hz, each fading over the
+        // octave above
+        for i in 0..=64 {
+            let freq = 50.0 * f32::powf(2.0, 6.0 * i as f32 / 64.0);
+            let w = 2.0 * std::f64::consts::PI * freq as f64 / sample_rate as f64;
+
+            analyzer.reset();
+
+            for n in 0..2 * FftSize::K32.size() {
+                analyzer.push(amplitude * f64::sin(w * n as f64) as f32);
+            }
+
+            let level = analyzer
+                .points()
+                .map(|(_, level)| level)
+                .fold(0.0, f32::max);
+
+            let error = 20.0 * f32::log10(level / amplitude);
+
+            assert!(error.abs() <= 0.5, "{freq} Hz reads {error:+.2} dB");
+        }
+    }
+}
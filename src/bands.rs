@@ -0,0 +1,109 @@
+use std::f64::consts::PI;
+
+use crate::{
+    dynamics::Dynamics,
+    filter::{Biquad, Filter, FilterDesign, FilterState, Placement, Precision},
+};
+
+/// The band chain, processed a block at a time.
+#[derive(Default)]
+pub struct Bands {
+    pub filters: [FilterState; 10],
+    pub dynamics: [Dynamics; 10],
+    /// The band being auditioned, if any.
+    pub solo: Option<usize>,
+    audition: Biquad,
+    audition_placement: Placement,
+}
+
+impl Bands {
+    pub fn set_params(
+        &mut self,
+        filters: &[Filter],
+        smoothing: f32,
+        design: FilterDesign,
+        precision: Precision,
+        sample_rate: f32,
+    ) {
+        let bands = self.filters.iter_mut().zip(self.dynamics.iter_mut());
+
+        for (filter, (state, dynamics)) in filters.iter().zip(bands) {
+            dynamics.set_params(filter, sample_rate);
+
+            state.set_smoothing(smoothing, sample_rate);
+            state.set_precision(precision);
+            state.set_params(filter, design, sample_rate);
+
+            if !dynamics.enabled {
+                state.set_dynamic_gain(0.0);
+            }
+        }
+    }
+
+    /// Solo the band at `solo` in `filters`, or stop soloing with `None`.
+    ///
+    /// This runs at the plugin rate, not the oversampled one.
+    pub fn set_solo(&mut self, solo: Option<usize>, filters: &[Filter], sample_rate: f32) {
+        if solo.is_some() && solo != self.solo {
+            self.audition.reset();
+        }
+
+        self.solo = solo;
+
+        let Some(filter) = solo.and_then(|solo| filters.get(solo)) else {
+            return;
+        };
+
+        let w0 = 2.0 * PI * *filter.freq as f64 / sample_rate as f64;
+
+        self.audition.set_band_pass(w0, *filter.q as f64);
+        self.audition_placement = filter.placement;
+    }
+
+    /// Replace `frames` with a band pass at the soloed band, only keeping
+    /// the signal the band processes.
+    pub fn audition(&mut self, frames: &mut [[f32; 2]], channels: usize) {
+        let placement = self.audition_placement;
+
+        for frame in frames.iter_mut() {
+            let (signals, count) = placement.encode(frame, channels);
+            let signals = self.audition.process(signals);
+
+            *frame = match count {
+                0 => [0.0; 2],
+                1 => [signals[0]; 2],
+                _ => signals,
+            };
+        }
+    }
+
+    /// Run `frames` through every band that isn't bypassed, `keys` is the
+    /// sidechain input with one frame per frame if it's connected.
+    pub fn process(&mut self, frames: &mut [[f32; 2]], keys: Option<&[[f32; 2]]>, channels: usize) {
+        let bands = self.filters.iter_mut().zip(self.dynamics.iter_mut());
+
+        for (filter, dynamics) in bands {
+            if filter.bypass(dynamics.enabled) {
+                continue;
+            }
+
+            if !dynamics.enabled {
+                filter.process_block(frames, channels);
+                continue;
+            }
+
+            let placement = filter.placement;
+
+            for (i, frame) in frames.iter_mut().enumerate() {
+                let (signals, count) = placement.encode(frame, channels);
+
+                let key = keys.map(|keys| &keys[i][..]);
+                let gain = dynamics.process(&signals[..count], key);
+                filter.set_dynamic_gain(gain);
+
+                let signals = filter.process(signals);
+                placement.decode(frame, channels, &signals);
+            }
+        }
+    }
+}
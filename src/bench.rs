@@ -0,0 +1,52 @@
+//! The band chain on its own, for `benches/bands.rs`. Only built with the
+//! `bench` feature, not part of the plugin.
+
+use crate::{
+    bands::Bands,
+    filter::{Filter, FilterDesign},
+};
+
+pub use crate::filter::Precision;
+
+/// Ten bands, the first `active` of them boosting by 6 dB and the rest at
+/// unity.
+pub struct BandChain {
+    bands: Bands,
+}
+
+impl BandChain {
+    pub fn new(active: usize, precision: Precision, sample_rate: f32) -> Self {
+        let filters: [Filter; 10] = std::array::from_fn(|i| {
+            let mut filter = Filter::new(i as u32, 10);
+
+            if i < active {
+                *filter.gain = 6.0;
+            }
+
+            filter
+        });
+
+        let mut bands = Bands::default();
+        bands.set_params(&filters, 20.0, FilterDesign::Rbj, precision, sample_rate);
+
+        Self { bands }
+    }
+
+    /// Process a block of stereo frames.
+    pub fn process(&mut self, frames: &mut [[f32; 2]]) {
+        self.bands.process(frames, None, 2);
+    }
+
+    /// Process a block of stereo frames one frame at a time through every
+    /// enabled band, the way the plugin did before the block processing, as
+    /// a baseline for `process`.
+    pub fn process_per_sample(&mut self, frames: &mut [[f32; 2]]) {
+        for frame in frames.iter_mut() {
+            for state in self.bands.filters.iter_mut() {
+                if state.enabled {
+                    *frame = state.process(*frame);
+                }
+            }
+        }
+    }
+}
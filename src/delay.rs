@@ -0,0 +1,27 @@
+/// A fixed delay of stereo frames.
+#[derive(Default)]
+pub struct Delay {
+    buffer: Vec<[f32; 2]>,
+    position: usize,
+}
+
+impl Delay {
+    pub fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![[0.0; 2]; length],
+            position: 0,
+        }
+    }
+
+    /// Delay `frames` in place.
+    pub fn process(&mut self, frames: &mut [[f32; 2]]) {
+        if self.buffer.is_empty() {
+            return;
+        }
+
+        for frame in frames.iter_mut() {
+            std::mem::swap(frame, &mut self.buffer[self.position]);
+            self.position = (self.position + 1) % self.buffer.len();
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use std::f64::consts::PI;
+
+use crate::filter::{Biquad, Detection, Direction, Filter};
+
+/// Envelope follower driving the gain of a dynamic band.
+///
+/// The detector listens to a band pass at the frequency and Q of the band,
+/// linked over both channels so the stereo image doesn't shift.
+pub struct Dynamics {
+    pub enabled: bool,
+    pub detection: Detection,
+    pub detector: Biquad,
+    pub threshold: f32,
+    pub ratio: f32,
+    pub range: f32,
+    pub direction: Direction,
+    pub attack: f32,
+    pub release: f32,
+    /// Mean square of the detector over the attack time.
+    pub power: f32,
+    /// `power` falling at the release time, the detected level.
+    pub envelope: f32,
+    /// Gain in dB to add to the band.
+    pub gain: f32,
+}
+
+impl Default for Dynamics {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            detection: Detection::Internal,
+            detector: Biquad::default(),
+            threshold: 0.0,
+            ratio: 1.0,
+            range: 0.0,
+            direction: Direction::Compress,
+            attack: 0.0,
+            release: 0.0,
+            power: 0.0,
+            envelope: 0.0,
+            gain: 0.0,
+        }
+    }
+}
+
+impl Dynamics {
+    pub fn set_params(&mut self, filter: &Filter, sample_rate: f32) {
+        let enabled = *filter.enabled && *filter.dynamic && filter.kind().uses_gain();
+
+        if !enabled && self.enabled {
+            self.power = 0.0;
+            self.envelope = 0.0;
+            self.gain = 0.0;
+        }
+
+        self.enabled = enabled;
+        self.detection = filter.detection;
+
+        let w0 = 2.0 * PI * *filter.freq as f64 / sample_rate as f64;
+
+        self.detector.set_band_pass(w0, *filter.q as f64);
+
+        self.threshold = *filter.threshold;
+        self.ratio = *filter.ratio;
+        self.range = *filter.range;
+        self.direction = filter.direction;
+        self.attack = Self::time_constant(*filter.attack, sample_rate);
+        self.release = Self::time_constant(*filter.release, sample_rate);
+    }
+
+    fn time_constant(ms: f32, sample_rate: f32) -> f32 {
+        f32::exp(-1000.0 / (ms * sample_rate))
+    }
+
+    /// Feed one sample per channel through the detector, returns the gain
+    /// in dB to add to the band.
+    ///
+    /// `sidechain` is used instead of `samples` for external detection when
+    /// the sidechain input is connected.
+    pub fn process(&mut self, samples: &[f32], sidechain: Option<&[f32]>) -> f32 {
+        let input = match (self.detection, sidechain) {
+            (Detection::External, Some(sidechain)) => sidechain,
+            _ => samples,
+        };
+
+        let mut lanes = [0.0; 2];
+        lanes[..input.len()].copy_from_slice(input);
+
+        let detected = self.detector.process(lanes);
+
+        let square = detected[..input.len()]
+            .iter()
+            .fold(0.0f32, |square, sample| square.max(sample * sample));
+
+        // smoothed while still linear, in dB every zero crossing would pull
+        // the level towards silence
+        self.power = square + self.attack * (self.power - square);
+
+        self.envelope = match self.power > self.envelope {
+            true => self.power,
+            false => self.power + self.release * (self.envelope - self.power),
+        };
+
+        // the mean square of a sine is half its peak squared, so a sine
+        // reads at its peak level
+        let level = 10.0 * f32::log10(2.0 * self.envelope + 1.0e-12);
+        let over = f32::max(level - self.threshold, 0.0);
+        let amount = f32::min(over * (1.0 - 1.0 / self.ratio), self.range);
+
+        self.gain = match self.direction {
+            Direction::Compress => -amount,
+            Direction::Expand => amount,
+        };
+
+        self.gain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Run a sine at the band frequency through `dynamics` for a second,
+    /// returns the gain it settles to.
+    fn settle(dynamics: &mut Dynamics, level: f32, freq: f32, sample_rate: f32) -> f32 {
+        let amplitude = f32::powf(10.0, level / 20.0);
+        let w = 2.0 * std::f32::consts::PI * freq / sample_rate;
+
+        let mut gain = 0.0;
+
+        for n in 0..sample_rate as usize {
+            let sample = amplitude * f32::sin(w * n as f32);
+            gain = dynamics.process(&[sample, sample], None);
+        }
+
+        gain
+    }
+
+    #[test]
+    fn sine_above_threshold_settles_to_the_ratio() {
+        let sample_rate = 48000.0;
+
+        for freq in [1000.0, 10000.0] {
+            for direction in [Direction::Compress, Direction::Expand] {
+                let mut filter = Filter::new(1, 3);
+                *filter.dynamic = true;
+                *filter.freq = freq;
+                *filter.threshold = -24.0;
+                *filter.ratio = 4.0;
+                *filter.range = 18.0;
+                filter.direction = direction;
+
+                let mut dynamics = Dynamics::default();
+                dynamics.set_params(&filter, sample_rate);
+
+                let gain = settle(&mut dynamics, -24.0 + 6.0, freq, sample_rate);
+                let expected = 6.0 * (1.0 - 1.0 / 4.0);
+
+                let expected = match direction {
+                    Direction::Compress => -expected,
+                    Direction::Expand => expected,
+                };
+
+                assert!(
+                    (gain - expected).abs() < 0.1,
+                    "{freq} Hz: {gain} dB, expected {expected} dB",
+                );
+            }
+        }
+    }
+}
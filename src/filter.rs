@@ -1,4 +1,5 @@
 use std::f32::consts::PI;
+use std::f64::consts::PI as PI_64;
 
 use num::Complex;
 use ori_vst::prelude::*;
@@ -10,6 +11,19 @@ pub struct Filter {
     pub gain: Float,
     pub q: Float,
     pub kind: FilterKind,
+    pub slope: Slope,
+    pub shape: Shape,
+    pub dynamic: Bool,
+    pub threshold: Float,
+    pub ratio: Float,
+    pub attack: Float,
+    pub release: Float,
+    pub range: Float,
+    pub direction: Direction,
+    pub detection: Detection,
+    pub placement: Placement,
+    /// Overrides `kind` with one of the kinds after `Notch` when set.
+    pub extra_kind: ExtraKind,
 }
 
 impl Filter {
@@ -19,6 +33,15 @@ impl Filter {
     pub const GAIN_MAX: f32 = 18.0;
     pub const Q_MIN: f32 = 0.1;
     pub const Q_MAX: f32 = 10.0;
+    pub const THRESHOLD_MIN: f32 = -60.0;
+    pub const THRESHOLD_MAX: f32 = 0.0;
+    pub const RATIO_MIN: f32 = 1.0;
+    pub const RATIO_MAX: f32 = 20.0;
+    pub const ATTACK_MIN: f32 = 0.1;
+    pub const ATTACK_MAX: f32 = 200.0;
+    pub const RELEASE_MIN: f32 = 1.0;
+    pub const RELEASE_MAX: f32 = 2000.0;
+    pub const RANGE_MAX: f32 = 18.0;
 
     pub fn new(index: u32, count: u32) -> Filter {
         // distribute the default frequency over the range
@@ -56,185 +79,865 @@ impl Filter {
                 .automate(),
 
             kind,
+
+            slope: Slope::Db6,
+            shape: Shape::Butterworth,
+
+            dynamic: Bool::new(false).name(format!("Dynamic ({})", index)),
+
+            threshold: Float::new(-24.0, Self::THRESHOLD_MIN..=Self::THRESHOLD_MAX)
+                .name(format!("Threshold ({})", index))
+                .automate(),
+
+            ratio: Float::new(2.0, Self::RATIO_MIN..=Self::RATIO_MAX)
+                .name(format!("Ratio ({})", index))
+                .automate(),
+
+            attack: Float::new(10.0, Self::ATTACK_MIN..=Self::ATTACK_MAX)
+                .name(format!("Attack ({})", index))
+                .automate(),
+
+            release: Float::new(100.0, Self::RELEASE_MIN..=Self::RELEASE_MAX)
+                .name(format!("Release ({})", index))
+                .automate(),
+
+            range: Float::new(6.0, 0.0..=Self::RANGE_MAX)
+                .name(format!("Range ({})", index))
+                .automate(),
+
+            direction: Direction::Compress,
+            detection: Detection::Internal,
+            placement: Placement::Stereo,
+            extra_kind: ExtraKind::None,
         }
     }
 
-    pub fn gain_at(&self, freq: f32, sample_rate: f32) -> f32 {
+    /// The kind the band runs as.
+    pub fn kind(&self) -> FilterKind {
+        self.extra_kind.kind().unwrap_or(self.kind)
+    }
+
+    /// Set the kind the band runs as, the original kinds go to `kind` and
+    /// the ones added after them to `extra_kind`.
+    pub fn set_kind(&mut self, kind: FilterKind) {
+        match ExtraKind::from_kind(kind) {
+            ExtraKind::None => {
+                self.kind = kind;
+                self.extra_kind = ExtraKind::None;
+            }
+            extra_kind => self.extra_kind = extra_kind,
+        }
+    }
+
+    pub fn gain_at(&self, freq: f32, sample_rate: f32, design: FilterDesign) -> f32 {
         let mut state = FilterState::default();
-        state.set_params(self, sample_rate);
+        state.set_params(self, design, sample_rate);
         state.gain_at(freq, sample_rate)
     }
 }
 
+/// A value that ramps linearly towards its target over a number of samples.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Smoothed {
+    pub current: f32,
+    pub target: f32,
+    step: f32,
+    remaining: u32,
+}
+
+impl Smoothed {
+    pub fn reset(&mut self, value: f32) {
+        self.current = value;
+        self.target = value;
+        self.step = 0.0;
+        self.remaining = 0;
+    }
+
+    pub fn set_target(&mut self, target: f32, ramp: u32) {
+        if target == self.target {
+            return;
+        }
+
+        if ramp == 0 {
+            self.reset(target);
+            return;
+        }
+
+        self.target = target;
+        self.step = (target - self.current) / ramp as f32;
+        self.remaining = ramp;
+    }
+
+    pub fn is_smoothing(&self) -> bool {
+        self.remaining > 0
+    }
+
+    pub fn advance(&mut self, samples: u32) -> f32 {
+        if samples >= self.remaining {
+            self.current = self.target;
+            self.remaining = 0;
+        } else {
+            self.current += self.step * samples as f32;
+            self.remaining -= samples;
+        }
+
+        self.current
+    }
+}
+
+/// A single second order section, run as a trapezoidal state variable filter,
+/// see Simper, "Solving the continuous SVF equations using trapezoidal
+/// integration and equivalent currents" (2013).
+///
+/// The states are the integrator currents rather than past samples, so the
+/// coefficients can change every sample without zipper noise or blowing up,
+/// and low frequencies don't lose precision like they do in direct form.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Biquad {
+    pub g: f64,
+    pub k: f64,
+    pub m0: f64,
+    pub m1: f64,
+    pub m2: f64,
+    pub single: Svf<f32>,
+    pub double: Svf<f64>,
+}
+
+impl Biquad {
+    /// Set the section from an analog prototype
+    /// `(m0 (s^2 + k s + 1) + m1 s + m2) / (s^2 + k s + 1)`, with `s`
+    /// normalized so that `g = tan(w / 2)` at the pole frequency `w`.
+    pub fn set_svf(&mut self, g: f64, k: f64, m0: f64, m1: f64, m2: f64) {
+        self.g = g;
+        self.k = k;
+        self.m0 = m0;
+        self.m1 = m1;
+        self.m2 = m2;
+
+        self.single.set(g, k, m0, m1, m2);
+        self.double.set(g, k, m0, m1, m2);
+    }
+
+    /// Set a first order section `(m0 (s + 1) + m1) / (s + 1)`, which is a
+    /// state variable filter with a double pole and a cancelling zero.
+    pub fn set_svf_1(&mut self, g: f64, m0: f64, m1: f64) {
+        self.set_svf(g, 2.0, m0, m1, m1);
+    }
+
+    /// Set the section from direct form coefficients, for designs that only
+    /// exist in the digital domain.
+    ///
+    /// Inverts the bilinear transform of `set_svf`. The terms cancel almost
+    /// completely at low frequencies, so the coefficients need to be f64.
+    /// Works for any stable section without a pole at nyquist.
+    pub fn set(&mut self, b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) {
+        let (b0, b1, b2) = (b0 / a0, b1 / a0, b2 / a0);
+        let (a1, a2) = (a1 / a0, a2 / a0);
+
+        let g = f64::sqrt((1.0 + a1 + a2) / (1.0 - a1 + a2));
+        let k = 2.0 * (1.0 - a2) / ((1.0 - a1 + a2) * g);
+        let d0 = 1.0 + g * k + g * g;
+
+        let m0 = (b0 - b1 + b2) * d0 / 4.0;
+        let m1 = (b0 - b2) * d0 / (2.0 * g) - m0 * k;
+        let m2 = (b0 + b1 + b2) * d0 / (4.0 * g * g) - m0;
+
+        self.set_svf(g, k, m0, m1, m2);
+    }
+
+    pub fn set_low_pass_1(&mut self, w0: f64) {
+        self.set_svf_1(f64::tan(w0 / 2.0), 0.0, 1.0);
+    }
+
+    pub fn set_high_pass_1(&mut self, w0: f64) {
+        self.set_svf_1(f64::tan(w0 / 2.0), 1.0, -1.0);
+    }
+
+    pub fn set_low_pass_2(&mut self, w0: f64, q: f64) {
+        self.set_svf(f64::tan(w0 / 2.0), 1.0 / q, 0.0, 0.0, 1.0);
+    }
+
+    pub fn set_high_pass_2(&mut self, w0: f64, q: f64) {
+        let k = 1.0 / q;
+
+        self.set_svf(f64::tan(w0 / 2.0), k, 1.0, -k, -1.0);
+    }
+
+    /// A low pass with a notch at `ratio` times the pole frequency, unity
+    /// gain at dc.
+    pub fn set_low_pass_notch(&mut self, w0: f64, q: f64, ratio: f64) {
+        let k = 1.0 / q;
+        let c = 1.0 / (ratio * ratio);
+
+        self.set_svf(f64::tan(w0 / 2.0), k, c, -c * k, 1.0 - c);
+    }
+
+    /// A high pass with a notch at `ratio` times the pole frequency, unity
+    /// gain at nyquist.
+    pub fn set_high_pass_notch(&mut self, w0: f64, q: f64, ratio: f64) {
+        let k = 1.0 / q;
+
+        self.set_svf(f64::tan(w0 / 2.0), k, 1.0, -k, ratio * ratio - 1.0);
+    }
+
+    pub fn set_band_pass(&mut self, w0: f64, q: f64) {
+        let k = 1.0 / q;
+
+        self.set_svf(f64::tan(w0 / 2.0), k, 0.0, k, 0.0);
+    }
+
+    /// Scale the output of the section by `gain`.
+    pub fn scale(&mut self, gain: f64) {
+        self.set_svf(
+            self.g,
+            self.k,
+            self.m0 * gain,
+            self.m1 * gain,
+            self.m2 * gain,
+        );
+    }
+
+    pub fn reset(&mut self) {
+        self.single.reset();
+        self.double.reset();
+    }
+
+    /// Carry the state over when switching to `precision`, so the switch
+    /// doesn't click.
+    pub fn switch_precision(&mut self, precision: Precision) {
+        match precision {
+            Precision::Single => {
+                self.single.ic1eq = self.double.ic1eq.map(|state| state as f32);
+                self.single.ic2eq = self.double.ic2eq.map(|state| state as f32);
+            }
+            Precision::Double => {
+                self.double.ic1eq = self.single.ic1eq.map(f64::from);
+                self.double.ic2eq = self.single.ic2eq.map(f64::from);
+            }
+        }
+    }
+
+    pub fn process(&mut self, samples: [f32; 2]) -> [f32; 2] {
+        self.single.process(samples)
+    }
+
+    /// The numerator and denominator of the prototype at the frequency with
+    /// half angle sine `sin` and cosine `cos`.
+    ///
+    /// Both are scaled by `(g cos)^2`, which cancels in the ratio, to stay
+    /// finite at nyquist and precise at low frequencies.
+    fn terms(&self, sin: f32, cos: f32) -> (Complex<f64>, Complex<f64>) {
+        let (sin, cos) = (sin as f64, cos as f64);
+        let g = self.g;
+
+        let re = g * g * cos * cos - sin * sin;
+        let im = g * sin * cos;
+
+        let den = Complex::new(re, self.k * im);
+        let num = den * self.m0 + Complex::new(self.m2 * g * g * cos * cos, self.m1 * im);
+
+        (num, den)
+    }
+
+    /// The complex response at the normalized angular frequency `w`.
+    pub fn response(&self, w: f32) -> Complex<f32> {
+        let (sin, cos) = f32::sin_cos(w / 2.0);
+        let (num, den) = self.terms(sin, cos);
+        let h = num / den;
+
+        Complex::new(h.re as f32, h.im as f32)
+    }
+
+    /// The squared magnitude at a frequency given by the sine and cosine of
+    /// half of `w`, cheaper than `response` when evaluating many frequencies.
+    pub fn power(&self, sin: f32, cos: f32) -> f32 {
+        let (num, den) = self.terms(sin, cos);
+
+        (num.norm_sqr() / den.norm_sqr()) as f32
+    }
+}
+
+/// The coefficients and state of a `Biquad` at one precision.
+///
+/// Runs two lanes with the same coefficients, one per channel, which the
+/// compiler can keep in a single vector register.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Svf<T> {
+    pub c1: T,
+    pub c2: T,
+    pub c3: T,
+    pub e1: T,
+    pub d2: T,
+    pub d3: T,
+    pub m0: T,
+    pub m1: T,
+    pub m2: T,
+    pub ic1eq: [T; 2],
+    pub ic2eq: [T; 2],
+}
+
+impl<T: num::Float> Svf<T> {
+    fn set(&mut self, g: f64, k: f64, m0: f64, m1: f64, m2: f64) {
+        let c1 = 1.0 / (1.0 + g * (g + k));
+
+        self.c1 = Self::cast(c1);
+        self.c2 = Self::cast(g * c1);
+        self.c3 = Self::cast(g * g * c1);
+        self.e1 = Self::cast(2.0 * c1 - 1.0);
+        self.d2 = Self::cast(2.0 * g * c1);
+        self.d3 = Self::cast(2.0 * g * g * c1);
+        self.m0 = Self::cast(m0);
+        self.m1 = Self::cast(m1);
+        self.m2 = Self::cast(m2);
+    }
+
+    fn cast(value: f64) -> T {
+        T::from(value).unwrap_or_else(T::zero)
+    }
+
+    pub fn reset(&mut self) {
+        self.ic1eq = [T::zero(); 2];
+        self.ic2eq = [T::zero(); 2];
+    }
+
+    pub fn process(&mut self, mut samples: [T; 2]) -> [T; 2] {
+        let states = self.ic1eq.iter_mut().zip(self.ic2eq.iter_mut());
+
+        for (sample, (ic1eq, ic2eq)) in samples.iter_mut().zip(states) {
+            let (ic1, ic2) = (*ic1eq, *ic2eq);
+
+            let v3 = *sample - ic2;
+            let v1 = self.c1 * ic1 + self.c2 * v3;
+            let v2 = ic2 + (self.c2 * ic1 + self.c3 * v3);
+
+            // `2 v - ic` expanded, which shortens the dependency chain
+            // between samples
+            *ic1eq = self.e1 * ic1 + self.d2 * v3;
+            *ic2eq = ic2 + (self.d2 * ic1 + self.d3 * v3);
+
+            *sample = self.m0 * *sample + self.m1 * v1 + self.m2 * v2;
+        }
+
+        samples
+    }
+
+    /// Process a block of samples, working on a copy so the state stays in
+    /// registers.
+    pub fn process_block(&mut self, samples: &mut [[T; 2]]) {
+        let mut svf = *self;
+
+        for sample in samples.iter_mut() {
+            *sample = svf.process(*sample);
+        }
+
+        self.ic1eq = svf.ic1eq;
+        self.ic2eq = svf.ic2eq;
+    }
+}
+
 #[derive(Default)]
 pub struct FilterState {
     pub enabled: bool,
-    pub b0: f32,
-    pub b1: f32,
-    pub b2: f32,
-    pub a0: f32,
-    pub a1: f32,
-    pub a2: f32,
-    pub z1: f32,
-    pub z2: f32,
-    pub y1: f32,
-    pub y2: f32,
+    pub kind: FilterKind,
+    pub slope: Slope,
+    pub shape: Shape,
+    pub placement: Placement,
+    pub design: FilterDesign,
+    pub precision: Precision,
+    pub sample_rate: f32,
+    /// Frequency in octaves, smoothed so that sweeps move evenly on a log scale.
+    pub freq: Smoothed,
+    pub gain: Smoothed,
+    /// Q in octaves, for the same reason as `freq`.
+    pub q: Smoothed,
+    pub ramp: u32,
+    pub countdown: u32,
+    pub initialized: bool,
+    /// Gain in dB added by the dynamics on top of the smoothed gain.
+    pub dynamic_gain: f32,
+    pub dynamic_changed: bool,
+    pub bypassed: bool,
+    pub sections: [Biquad; FilterState::MAX_SECTIONS],
+    pub section_count: usize,
 }
 
 impl FilterState {
-    pub fn set_params(&mut self, filter: &Filter, sample_rate: f32) {
-        let freq = *filter.freq;
+    /// Number of samples between coefficient updates while parameters are ramping.
+    pub const SMOOTHING_INTERVAL: u32 = 16;
+
+    /// Enough sections for the steepest slope.
+    pub const MAX_SECTIONS: usize = 8;
+
+    /// Frames per chunk of `process_block`.
+    pub const BLOCK_SIZE: usize = 64;
+
+    /// Set the time it takes for parameter changes to ramp to their new value.
+    pub fn set_smoothing(&mut self, ramp_ms: f32, sample_rate: f32) {
+        self.ramp = (ramp_ms * sample_rate / 1000.0).round() as u32;
+    }
+
+    pub fn set_precision(&mut self, precision: Precision) {
+        if self.precision.id() == precision.id() {
+            return;
+        }
+
+        self.precision = precision;
+
+        for section in self.sections.iter_mut() {
+            section.switch_precision(precision);
+        }
+    }
+
+    pub fn set_params(&mut self, filter: &Filter, design: FilterDesign, sample_rate: f32) {
+        let freq = filter.freq.log2();
         let gain = *filter.gain;
-        let q = *filter.q;
+        let q = filter.q.log2();
+
+        // changes to the kind, the slope, the shape, the design, the sample
+        // rate or the enabled state cannot be ramped, so the state snaps
+        // directly to the new parameters
+        let snap = !self.initialized
+            || self.kind.id() != filter.kind().id()
+            || self.slope.id() != filter.slope.id()
+            || self.shape.id() != filter.shape.id()
+            || self.design.id() != design.id()
+            || self.sample_rate != sample_rate
+            || self.enabled != *filter.enabled;
+
+        // the sections hold the state of the signals of the old placement,
+        // the new one would pick it up as if it were its own
+        if self.initialized && self.placement.id() != filter.placement.id() {
+            for section in self.sections.iter_mut() {
+                section.reset();
+            }
+        }
 
         self.enabled = *filter.enabled;
-        self.set_params_inner(freq, gain, q, filter.kind, sample_rate);
+        self.kind = filter.kind();
+        self.slope = filter.slope;
+        self.shape = filter.shape;
+        self.placement = filter.placement;
+        self.design = design;
+        self.sample_rate = sample_rate;
+        self.initialized = true;
+
+        if snap {
+            self.freq.reset(freq);
+            self.gain.reset(gain);
+            self.q.reset(q);
+            self.countdown = 0;
+
+            let section_count = self.section_count;
+            self.update_coefficients();
+
+            // sections that weren't running hold stale state
+            for section in self.sections[section_count..].iter_mut() {
+                section.reset();
+            }
+
+            return;
+        }
+
+        self.freq.set_target(freq, self.ramp);
+        self.gain.set_target(gain, self.ramp);
+        self.q.set_target(q, self.ramp);
+    }
+
+    /// Set the gain added by the dynamics, the coefficients follow at the
+    /// next smoothing interval.
+    pub fn set_dynamic_gain(&mut self, gain: f32) {
+        if (gain - self.dynamic_gain).abs() < 0.01 {
+            return;
+        }
+
+        self.dynamic_gain = gain;
+        self.dynamic_changed = true;
+    }
+
+    fn is_smoothing(&self) -> bool {
+        self.freq.is_smoothing() || self.gain.is_smoothing() || self.q.is_smoothing()
+    }
+
+    /// Design the sections for the current parameters.
+    ///
+    /// The design runs in f64 at either precision, at 20 Hz and 192 kHz
+    /// `tan(w0 / 2)` is around 3e-4 and f32 would round the pole frequency
+    /// and Q of the sections noticeably. Only the final coefficients of the
+    /// single precision sections are rounded to f32.
+    fn update_coefficients(&mut self) {
+        let freq = f64::powf(2.0, self.freq.current as f64);
+        let gain = (self.gain.current + self.dynamic_gain) as f64;
+        let q = f64::powf(2.0, self.q.current as f64);
+
+        self.set_params_inner(freq, gain, q, self.kind, self.sample_rate as f64);
+    }
+
+    fn advance_smoothing(&mut self) {
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return;
+        }
+
+        if !self.is_smoothing() && !self.dynamic_changed {
+            return;
+        }
+
+        self.dynamic_changed = false;
+
+        self.freq.advance(Self::SMOOTHING_INTERVAL);
+        self.gain.advance(Self::SMOOTHING_INTERVAL);
+        self.q.advance(Self::SMOOTHING_INTERVAL);
+
+        self.update_coefficients();
+        self.countdown = Self::SMOOTHING_INTERVAL - 1;
     }
 
     fn set_params_inner(
         &mut self,
-        freq: f32,
-        gain: f32,
-        q: f32,
+        freq: f64,
+        gain: f64,
+        q: f64,
         kind: FilterKind,
-        sample_rate: f32,
+        sample_rate: f64,
     ) {
-        let a = f32::powf(10.0, gain / 40.0);
+        let a = f64::powf(10.0, gain / 40.0);
+
+        let w0 = 2.0 * PI_64 * freq / sample_rate;
+
+        self.section_count = 1;
+
+        if let FilterDesign::Matched = self.design {
+            if self.set_matched(w0, a, q, kind) {
+                return;
+            }
+        }
 
-        let w0 = 2.0 * PI * freq / sample_rate;
-        let cos_w0 = w0.cos();
+        let section = &mut self.sections[0];
+
+        let g = f64::tan(w0 / 2.0);
+        let k = 1.0 / q;
 
         match kind {
             FilterKind::LowPass => {
-                let w0 = f32::tan(w0 / 2.0);
-
-                self.b0 = w0;
-                self.b1 = w0;
-                self.b2 = 0.0;
-                self.a0 = w0 + 1.0;
-                self.a1 = w0 - 1.0;
-                self.a2 = 0.0;
+                self.set_cascade(w0, false);
             }
             FilterKind::LowPass2 => {
-                let alpha = w0.sin() / (2.0 * q);
-
-                self.b0 = (1.0 - cos_w0) / 2.0;
-                self.b1 = 1.0 - cos_w0;
-                self.b2 = (1.0 - cos_w0) / 2.0;
-                self.a0 = 1.0 + alpha;
-                self.a1 = -2.0 * cos_w0;
-                self.a2 = 1.0 - alpha;
+                section.set_low_pass_2(w0, q);
             }
             FilterKind::LowShelf => {
-                let alpha = w0.sin() / 2.0 * (1.0 / q);
-
-                self.b0 = a * ((a + 1.0) - (a - 1.0) * cos_w0 + 2.0 * a.sqrt() * alpha);
-                self.b1 = 2.0 * a * ((a - 1.0) - (a + 1.0) * cos_w0);
-                self.b2 = a * ((a + 1.0) - (a - 1.0) * cos_w0 - 2.0 * a.sqrt() * alpha);
-                self.a0 = (a + 1.0) + (a - 1.0) * cos_w0 + 2.0 * a.sqrt() * alpha;
-                self.a1 = -2.0 * ((a - 1.0) + (a + 1.0) * cos_w0);
-                self.a2 = (a + 1.0) + (a - 1.0) * cos_w0 - 2.0 * a.sqrt() * alpha;
+                section.set_svf(g / a.sqrt(), k, 1.0, k * (a - 1.0), a * a - 1.0);
             }
             FilterKind::HighPass => {
-                let w0 = f32::tan(w0 / 2.0);
-
-                self.b0 = 1.0;
-                self.b1 = -1.0;
-                self.b2 = 0.0;
-                self.a0 = w0 + 1.0;
-                self.a1 = w0 - 1.0;
-                self.a2 = 0.0;
+                self.set_cascade(w0, true);
             }
             FilterKind::HighPass2 => {
-                let alpha = w0.sin() / (2.0 * q);
-
-                self.b0 = (1.0 + cos_w0) / 2.0;
-                self.b1 = -(1.0 + cos_w0);
-                self.b2 = (1.0 + cos_w0) / 2.0;
-                self.a0 = 1.0 + alpha;
-                self.a1 = -2.0 * cos_w0;
-                self.a2 = 1.0 - alpha;
+                section.set_high_pass_2(w0, q);
             }
             FilterKind::HighShelf => {
-                let alpha = w0.sin() / 2.0 * (1.0 / q);
-
-                self.b0 = a * ((a + 1.0) + (a - 1.0) * cos_w0 + 2.0 * a.sqrt() * alpha);
-                self.b1 = -2.0 * a * ((a - 1.0) + (a + 1.0) * cos_w0);
-                self.b2 = a * ((a + 1.0) + (a - 1.0) * cos_w0 - 2.0 * a.sqrt() * alpha);
-                self.a0 = (a + 1.0) - (a - 1.0) * cos_w0 + 2.0 * a.sqrt() * alpha;
-                self.a1 = 2.0 * ((a - 1.0) - (a + 1.0) * cos_w0);
-                self.a2 = (a + 1.0) - (a - 1.0) * cos_w0 - 2.0 * a.sqrt() * alpha;
+                section.set_svf(g * a.sqrt(), k, a * a, k * (1.0 - a) * a, 1.0 - a * a);
             }
             FilterKind::Peak => {
-                let alpha = f32::sin(w0) / (2.0 * q);
+                let k = 1.0 / (q * a);
+
+                section.set_svf(g, k, 1.0, k * (a * a - 1.0), 0.0);
+            }
+            FilterKind::Notch => {
+                section.set_svf(g, k, 1.0, -k, 0.0);
+            }
+            FilterKind::BandPass => {
+                section.set_band_pass(w0, q);
+            }
+            FilterKind::BandPassSkirt => {
+                section.set_svf(g, k, 0.0, 1.0, 0.0);
+            }
+            FilterKind::AllPass => {
+                section.set_svf_1(g, -1.0, 2.0);
+            }
+            FilterKind::AllPass2 => {
+                section.set_svf(g, k, 1.0, -2.0 * k, 0.0);
+            }
+            FilterKind::Tilt => {
+                // (a s + 1) / (s + a), -gain / 2 at dc and gain / 2 at
+                // infinity with unity gain at the band frequency
+                section.set_svf_1(g * a, a, (1.0 - a * a) / a);
+            }
+        }
+    }
+
+    /// Design a low or high pass with the order of `self.slope` and the
+    /// response of `self.shape`, as a cascade of first and second order
+    /// sections.
+    fn set_cascade(&mut self, w0: f64, high_pass: bool) {
+        let mut poles = [Pole::Real(1.0); Self::MAX_SECTIONS];
+        let count = self.shape.prototype(self.slope.order(), &mut poles);
+
+        // every section is prewarped at the cutoff, so the response of the
+        // prototype lands exactly on the band frequency
+        let k = f64::tan(w0 / 2.0);
+
+        for (section, pole) in self.sections.iter_mut().zip(&poles[..count]) {
+            let warp = |freq: f32| match high_pass {
+                true => 2.0 * f64::atan(k / freq as f64),
+                false => 2.0 * f64::atan(k * freq as f64),
+            };
+
+            match (*pole, high_pass) {
+                (Pole::Real(freq), true) => section.set_high_pass_1(warp(freq)),
+                (Pole::Real(freq), false) => section.set_low_pass_1(warp(freq)),
+                (Pole::Pair { freq, q }, true) => section.set_high_pass_2(warp(freq), q as f64),
+                (Pole::Pair { freq, q }, false) => section.set_low_pass_2(warp(freq), q as f64),
+                (Pole::NotchPair { freq, q, zero }, true) => {
+                    section.set_high_pass_notch(warp(freq), q as f64, (freq / zero) as f64)
+                }
+                (Pole::NotchPair { freq, q, zero }, false) => {
+                    section.set_low_pass_notch(warp(freq), q as f64, (zero / freq) as f64)
+                }
+            }
+        }
+
+        let gain = self.shape.passband_gain(self.slope.order()) as f64;
+
+        self.sections[0].scale(gain);
 
-                self.b0 = 1.0 + alpha * a;
-                self.b1 = -2.0 * cos_w0;
-                self.b2 = 1.0 - alpha * a;
-                self.a0 = 1.0 + alpha / a;
-                self.a1 = -2.0 * cos_w0;
-                self.a2 = 1.0 - alpha / a;
+        self.section_count = count;
+    }
+
+    /// Design `kind` by matching the magnitude of the analog prototype, see
+    /// Vicanek, "Matched Second Order Digital Filters" (2016).
+    ///
+    /// Unlike the bilinear transform this doesn't cramp the response near
+    /// nyquist. Returns `false` if `kind` has no matched design.
+    fn set_matched(&mut self, w0: f64, a: f64, q: f64, kind: FilterKind) -> bool {
+        // the analog prototypes are the ones the RBJ formulas are derived from,
+        // expressed as squared magnitudes at `x = w / w0`
+        let (w_pole, q_pole): (f64, f64);
+        let magnitude: fn(f64, f64, f64) -> f64;
+
+        match kind {
+            FilterKind::Peak => {
+                w_pole = w0;
+                q_pole = q * a;
+                magnitude = |x, a, q| {
+                    let x2 = x * x;
+                    let num = (1.0 - x2).powi(2) + (a * x / q).powi(2);
+                    let den = (1.0 - x2).powi(2) + (x / (a * q)).powi(2);
+                    num / den
+                };
+            }
+            FilterKind::LowShelf => {
+                w_pole = w0 / a.sqrt();
+                q_pole = q;
+                magnitude = |x, a, q| {
+                    let x2 = x * x;
+                    let num = (a - x2).powi(2) + a * x2 / (q * q);
+                    let den = (1.0 - a * x2).powi(2) + a * x2 / (q * q);
+                    a * a * num / den
+                };
+            }
+            FilterKind::HighShelf => {
+                w_pole = w0 * a.sqrt();
+                q_pole = q;
+                magnitude = |x, a, q| {
+                    let x2 = x * x;
+                    let num = (1.0 - a * x2).powi(2) + a * x2 / (q * q);
+                    let den = (a - x2).powi(2) + a * x2 / (q * q);
+                    a * a * num / den
+                };
             }
             FilterKind::Notch => {
-                let alpha = f32::sin(w0) / (2.0 * q);
+                let (a1, a2) = Self::matched_poles(w0, q);
+
+                // place the zeros on the unit circle at w0 and normalize the
+                // gain at dc to unity
+                let b0 = (1.0 + a1 + a2) / (2.0 - 2.0 * w0.cos());
 
-                self.b0 = 1.0;
-                self.b1 = -2.0 * cos_w0;
-                self.b2 = 1.0;
-                self.a0 = 1.0 + alpha;
-                self.a1 = -2.0 * cos_w0;
-                self.a2 = 1.0 - alpha;
+                self.sections[0].set(b0, -2.0 * w0.cos() * b0, b0, 1.0, a1, a2);
+
+                return true;
             }
+            _ => return false,
         }
 
-        self.b0 /= self.a0;
-        self.b1 /= self.a0;
-        self.b2 /= self.a0;
-        self.a1 /= self.a0;
-        self.a2 /= self.a0;
+        let (a1, a2) = Self::matched_poles(w_pole, q_pole);
+
+        let a0_sq = (1.0 + a1 + a2).powi(2);
+        let a1_sq = (1.0 - a1 + a2).powi(2);
+        let a2_sq = -4.0 * a2;
+
+        // squared magnitudes at dc, at nyquist and at the match frequency,
+        // which is kept away from nyquist where the system becomes singular
+        let w_match = f64::min(w0, 0.95 * PI_64);
+
+        let h0 = magnitude(0.0, a, q);
+        let h1 = magnitude(PI_64 / w0, a, q);
+        let hw = magnitude(w_match / w0, a, q);
+
+        let phi1 = f64::sin(w_match / 2.0).powi(2);
+        let phi0 = 1.0 - phi1;
+        let phi2 = 4.0 * phi0 * phi1;
+
+        let b0_sq = a0_sq * h0;
+        let b1_sq = a1_sq * h1;
+        let b2_sq =
+            (hw * (a0_sq * phi0 + a1_sq * phi1 + a2_sq * phi2) - b0_sq * phi0 - b1_sq * phi1)
+                / phi2;
+
+        let b0_sqrt = b0_sq.sqrt();
+        let b1_sqrt = b1_sq.sqrt();
+        let w = (b0_sqrt + b1_sqrt) / 2.0;
+
+        let b0 = (w + f64::max(w * w + b2_sq, 0.0).sqrt()) / 2.0;
+
+        let b1 = (b0_sqrt - b1_sqrt) / 2.0;
+        let b2 = -b2_sq / (4.0 * b0);
+
+        self.sections[0].set(b0, b1, b2, 1.0, a1, a2);
+
+        true
+    }
+
+    /// Impulse invariant poles of an analog resonator with frequency `w` and quality `q`.
+    fn matched_poles(w: f64, q: f64) -> (f64, f64) {
+        let zeta = 1.0 / (2.0 * q);
+        let r = f64::exp(-zeta * w);
+
+        let a1 = match zeta <= 1.0 {
+            true => -2.0 * r * f64::cos(w * f64::sqrt(1.0 - zeta * zeta)),
+            false => -2.0 * r * f64::cosh(w * f64::sqrt(zeta * zeta - 1.0)),
+        };
+
+        (a1, r * r)
     }
 
-    pub fn process(&mut self, sample: f32) -> f32 {
-        let b0 = self.b0;
-        let b1 = self.b1;
-        let b2 = self.b2;
-        let a1 = self.a1;
-        let a2 = self.a2;
-        let z1 = self.z1;
-        let z2 = self.z2;
-        let y1 = self.y1;
-        let y2 = self.y2;
+    /// Whether the band can be skipped, either because it's disabled or sits
+    /// at unity gain. `dynamic` bands are never at unity as their gain can
+    /// move at any sample.
+    ///
+    /// The sections are reset when the band stops being bypassed, so they
+    /// don't start from stale state.
+    pub fn bypass(&mut self, dynamic: bool) -> bool {
+        let unity = !dynamic
+            && self.kind.uses_gain()
+            && !self.is_smoothing()
+            && !self.dynamic_changed
+            && self.gain.current + self.dynamic_gain == 0.0;
 
-        let out = b0 * sample + b1 * z1 + b2 * z2 - a1 * y1 - a2 * y2;
+        let bypass = !self.enabled || unity;
 
-        self.z2 = z1;
-        self.z1 = sample;
-        self.y2 = y1;
-        self.y1 = out;
+        if self.bypassed && !bypass {
+            for section in self.sections.iter_mut() {
+                section.reset();
+            }
+        }
 
-        out
+        self.bypassed = bypass;
+        bypass
     }
 
-    pub fn gain_at(&self, freq: f32, sample_rate: f32) -> f32 {
-        let a0 = self.a0;
+    /// Process `frames` with the placement of the band, running one section
+    /// at a time over chunks of the block.
+    ///
+    /// Dynamic bands need `process` instead, as their gain can change every
+    /// sample.
+    pub fn process_block(&mut self, frames: &mut [[f32; 2]], channels: usize) {
+        let mut signals = [[0.0; 2]; Self::BLOCK_SIZE];
+        let mut frames = frames;
+
+        while !frames.is_empty() {
+            self.advance_smoothing();
+
+            // the coefficients now hold for `countdown` more samples while
+            // ramping, or for the rest of the block once they've settled
+            let len = match self.is_smoothing() {
+                true => usize::min(self.countdown as usize + 1, frames.len()),
+                false => frames.len(),
+            };
+
+            let len = usize::min(len, Self::BLOCK_SIZE);
+
+            self.countdown = self.countdown.saturating_sub(len as u32 - 1);
+
+            let (chunk, rest) = std::mem::take(&mut frames).split_at_mut(len);
+            frames = rest;
+
+            let signals = &mut signals[..len];
+
+            for (signal, frame) in signals.iter_mut().zip(chunk.iter()) {
+                *signal = self.placement.encode(frame, channels).0;
+            }
+
+            let sections = self.sections[..self.section_count].iter_mut();
+
+            match self.precision {
+                Precision::Single => {
+                    for section in sections {
+                        section.single.process_block(signals);
+                    }
+                }
+                Precision::Double => {
+                    let mut wide = [[0.0; 2]; Self::BLOCK_SIZE];
+                    let wide = &mut wide[..len];
+
+                    for (wide, signal) in wide.iter_mut().zip(signals.iter()) {
+                        *wide = signal.map(f64::from);
+                    }
+
+                    for section in sections {
+                        section.double.process_block(wide);
+                    }
+
+                    for (signal, wide) in signals.iter_mut().zip(wide.iter()) {
+                        *signal = wide.map(|sample| sample as f32);
+                    }
+                }
+            }
+
+            for (frame, signal) in chunk.iter_mut().zip(signals.iter()) {
+                self.placement.decode(frame, channels, signal);
+            }
+        }
+    }
+
+    /// Process one sample per lane, see `Svf`.
+    pub fn process(&mut self, samples: [f32; 2]) -> [f32; 2] {
+        self.advance_smoothing();
 
-        let b0 = self.b0 * a0;
-        let b1 = self.b1 * a0;
-        let b2 = self.b2 * a0;
-        let a1 = self.a1 * a0;
-        let a2 = self.a2 * a0;
+        let sections = self.sections[..self.section_count].iter_mut();
+
+        match self.precision {
+            Precision::Single => sections.fold(samples, |out, section| section.single.process(out)),
+            Precision::Double => {
+                let out = sections.fold(samples.map(f64::from), |out, section| {
+                    section.double.process(out)
+                });
+
+                out.map(|sample| sample as f32)
+            }
+        }
+    }
 
+    pub fn gain_at(&self, freq: f32, sample_rate: f32) -> f32 {
         let w = 2.0 * PI * freq / sample_rate;
 
-        let num = b0 + b1 * Complex::new(0.0, -w).exp() + b2 * Complex::new(0.0, -2.0 * w).exp();
-        let den = a0 + a1 * Complex::new(0.0, -w).exp() + a2 * Complex::new(0.0, -2.0 * w).exp();
-        let h = num / den;
+        let h = self.sections[..self.section_count]
+            .iter()
+            .fold(Complex::new(1.0, 0.0), |h, section| h * section.response(w));
 
         20.0 * f32::log10(h.norm())
     }
+
+    /// The linear magnitude at a frequency given by the sine and cosine of
+    /// half of `w`.
+    pub fn magnitude(&self, sin: f32, cos: f32) -> f32 {
+        let power = self.sections[..self.section_count]
+            .iter()
+            .fold(1.0, |power, section| power * section.power(sin, cos));
+
+        power.sqrt()
+    }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, Default)]
 pub enum FilterKind {
     LowPass,
     LowPass2,
@@ -242,12 +945,27 @@ pub enum FilterKind {
     HighPass,
     HighPass2,
     HighShelf,
+    #[default]
     Peak,
     Notch,
+    /// Band pass with a constant peak gain of 0 dB.
+    BandPass,
+    /// Band pass with a constant skirt gain, the peak gain follows Q.
+    BandPassSkirt,
+    AllPass,
+    AllPass2,
+    /// First order tilt around the band frequency, the ends are at plus and
+    /// minus half the gain.
+    Tilt,
 }
 
 impl FilterKind {
-    pub const MAX_ID: u32 = 7;
+    pub const MAX_ID: u32 = 12;
+
+    /// The last id of the `kind` param. The kinds after it are set through
+    /// `ExtraKind`, so the normalized values of the kinds before them, which
+    /// sessions and automation hold on to, keep their meaning.
+    pub const PARAM_MAX_ID: u32 = 7;
 
     pub fn abbreviation(&self) -> &str {
         match self {
@@ -259,9 +977,18 @@ impl FilterKind {
             FilterKind::HighShelf => "HS",
             FilterKind::Peak => "PK",
             FilterKind::Notch => "NT",
+            FilterKind::BandPass => "BP",
+            FilterKind::BandPassSkirt => "BPS",
+            FilterKind::AllPass => "AP",
+            FilterKind::AllPass2 => "AP2",
+            FilterKind::Tilt => "TL",
         }
     }
 
+    pub fn uses_slope(&self) -> bool {
+        matches!(self, FilterKind::LowPass | FilterKind::HighPass)
+    }
+
     pub fn uses_gain(&self) -> bool {
         match self {
             FilterKind::LowPass => false,
@@ -272,6 +999,11 @@ impl FilterKind {
             FilterKind::HighShelf => true,
             FilterKind::Peak => true,
             FilterKind::Notch => false,
+            FilterKind::BandPass => false,
+            FilterKind::BandPassSkirt => false,
+            FilterKind::AllPass => false,
+            FilterKind::AllPass2 => false,
+            FilterKind::Tilt => true,
         }
     }
 
@@ -285,6 +1017,11 @@ impl FilterKind {
             FilterKind::HighShelf => 5,
             FilterKind::Peak => 6,
             FilterKind::Notch => 7,
+            FilterKind::BandPass => 8,
+            FilterKind::BandPassSkirt => 9,
+            FilterKind::AllPass => 10,
+            FilterKind::AllPass2 => 11,
+            FilterKind::Tilt => 12,
         }
     }
 
@@ -298,6 +1035,11 @@ impl FilterKind {
             5 => Some(FilterKind::HighShelf),
             6 => Some(FilterKind::Peak),
             7 => Some(FilterKind::Notch),
+            8 => Some(FilterKind::BandPass),
+            9 => Some(FilterKind::BandPassSkirt),
+            10 => Some(FilterKind::AllPass),
+            11 => Some(FilterKind::AllPass2),
+            12 => Some(FilterKind::Tilt),
             _ => None,
         }
     }
@@ -321,7 +1063,10 @@ impl Param for FilterKind {
     }
 
     fn set(&mut self, plain: f32) {
-        *self = FilterKind::from_id(plain.round() as u32).unwrap_or(FilterKind::Peak);
+        *self = match FilterKind::from_id(plain.round() as u32) {
+            Some(kind) if kind.id() <= Self::PARAM_MAX_ID => kind,
+            _ => FilterKind::Peak,
+        };
     }
 
     fn default(&self) -> f32 {
@@ -329,11 +1074,11 @@ impl Param for FilterKind {
     }
 
     fn plain(&self, normalized: f32) -> f32 {
-        normalized * Self::MAX_ID as f32
+        normalized * Self::PARAM_MAX_ID as f32
     }
 
     fn normalize(&self, plain: f32) -> f32 {
-        plain / Self::MAX_ID as f32
+        plain / Self::PARAM_MAX_ID as f32
     }
 
     fn unit(&self) -> Unit {
@@ -341,7 +1086,7 @@ impl Param for FilterKind {
     }
 
     fn steps(&self) -> Option<i32> {
-        Some(Self::MAX_ID as i32)
+        Some(Self::PARAM_MAX_ID as i32)
     }
 
     fn flags(&self) -> ParamFlags {
@@ -359,6 +1104,7 @@ impl Param for FilterKind {
                 FilterKind::HighShelf => String::from("High Shelf"),
                 FilterKind::Peak => String::from("Peak"),
                 FilterKind::Notch => String::from("Notch"),
+                _ => ExtraKind::from_kind(kind).name(),
             },
             None => {
                 println!("FilterKind::to_string: invalid id: {}", plain);
@@ -381,3 +1127,1442 @@ impl Param for FilterKind {
         }
     }
 }
+
+/// The kinds added after `Notch`, a separate param so that the `kind` param
+/// keeps its range.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ExtraKind {
+    /// Run as `kind`.
+    #[default]
+    None,
+    BandPass,
+    BandPassSkirt,
+    AllPass,
+    AllPass2,
+    Tilt,
+}
+
+impl ExtraKind {
+    pub const MAX_ID: u32 = 5;
+
+    pub fn kind(&self) -> Option<FilterKind> {
+        match self {
+            ExtraKind::None => None,
+            ExtraKind::BandPass => Some(FilterKind::BandPass),
+            ExtraKind::BandPassSkirt => Some(FilterKind::BandPassSkirt),
+            ExtraKind::AllPass => Some(FilterKind::AllPass),
+            ExtraKind::AllPass2 => Some(FilterKind::AllPass2),
+            ExtraKind::Tilt => Some(FilterKind::Tilt),
+        }
+    }
+
+    /// The extra kind of `kind`, `None` for the original kinds.
+    pub fn from_kind(kind: FilterKind) -> ExtraKind {
+        match kind {
+            FilterKind::BandPass => ExtraKind::BandPass,
+            FilterKind::BandPassSkirt => ExtraKind::BandPassSkirt,
+            FilterKind::AllPass => ExtraKind::AllPass,
+            FilterKind::AllPass2 => ExtraKind::AllPass2,
+            FilterKind::Tilt => ExtraKind::Tilt,
+            _ => ExtraKind::None,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            ExtraKind::None => String::from("None"),
+            ExtraKind::BandPass => String::from("Band Pass"),
+            ExtraKind::BandPassSkirt => String::from("Band Pass Skirt"),
+            ExtraKind::AllPass => String::from("All Pass"),
+            ExtraKind::AllPass2 => String::from("All Pass 2"),
+            ExtraKind::Tilt => String::from("Tilt"),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            ExtraKind::None => 0,
+            ExtraKind::BandPass => 1,
+            ExtraKind::BandPassSkirt => 2,
+            ExtraKind::AllPass => 3,
+            ExtraKind::AllPass2 => 4,
+            ExtraKind::Tilt => 5,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<ExtraKind> {
+        match id {
+            0 => Some(ExtraKind::None),
+            1 => Some(ExtraKind::BandPass),
+            2 => Some(ExtraKind::BandPassSkirt),
+            3 => Some(ExtraKind::AllPass),
+            4 => Some(ExtraKind::AllPass2),
+            5 => Some(ExtraKind::Tilt),
+            _ => None,
+        }
+    }
+}
+
+impl Param for ExtraKind {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = ExtraKind::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::None.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match ExtraKind::from_id(plain.round() as u32) {
+            Some(kind) => kind.name(),
+            None => {
+                println!("ExtraKind::to_string: invalid id: {}", plain);
+                String::from("None")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Band Pass" => ExtraKind::BandPass.get(),
+            "Band Pass Skirt" => ExtraKind::BandPassSkirt.get(),
+            "All Pass" => ExtraKind::AllPass.get(),
+            "All Pass 2" => ExtraKind::AllPass2.get(),
+            "Tilt" => ExtraKind::Tilt.get(),
+            _ => ExtraKind::None.get(),
+        }
+    }
+}
+
+/// The steepness of the `LowPass` and `HighPass` kinds.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Slope {
+    #[default]
+    Db6,
+    Db12,
+    Db18,
+    Db24,
+    Db36,
+    Db48,
+    Db72,
+    Db96,
+}
+
+impl Slope {
+    pub const MAX_ID: u32 = 7;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Slope::Db6 => "6 dB",
+            Slope::Db12 => "12 dB",
+            Slope::Db18 => "18 dB",
+            Slope::Db24 => "24 dB",
+            Slope::Db36 => "36 dB",
+            Slope::Db48 => "48 dB",
+            Slope::Db72 => "72 dB",
+            Slope::Db96 => "96 dB",
+        }
+    }
+
+    /// The order of the filter, 6 dB/oct per order.
+    pub fn order(&self) -> usize {
+        match self {
+            Slope::Db6 => 1,
+            Slope::Db12 => 2,
+            Slope::Db18 => 3,
+            Slope::Db24 => 4,
+            Slope::Db36 => 6,
+            Slope::Db48 => 8,
+            Slope::Db72 => 12,
+            Slope::Db96 => 16,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Slope::Db6 => 0,
+            Slope::Db12 => 1,
+            Slope::Db18 => 2,
+            Slope::Db24 => 3,
+            Slope::Db36 => 4,
+            Slope::Db48 => 5,
+            Slope::Db72 => 6,
+            Slope::Db96 => 7,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Slope> {
+        match id {
+            0 => Some(Slope::Db6),
+            1 => Some(Slope::Db12),
+            2 => Some(Slope::Db18),
+            3 => Some(Slope::Db24),
+            4 => Some(Slope::Db36),
+            5 => Some(Slope::Db48),
+            6 => Some(Slope::Db72),
+            7 => Some(Slope::Db96),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Slope {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Slope::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Slope {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Slope::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Db6.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::from("dB/oct"))
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST | ParamFlags::AUTOMATE
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Slope::from_id(plain.round() as u32) {
+            Some(slope) => String::from(slope.abbreviation()),
+            None => {
+                println!("Slope::to_string: invalid id: {}", plain);
+                String::from("6 dB")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "6 dB" => Slope::Db6.get(),
+            "12 dB" => Slope::Db12.get(),
+            "18 dB" => Slope::Db18.get(),
+            "24 dB" => Slope::Db24.get(),
+            "36 dB" => Slope::Db36.get(),
+            "48 dB" => Slope::Db48.get(),
+            "72 dB" => Slope::Db72.get(),
+            "96 dB" => Slope::Db96.get(),
+            _ => Slope::Db6.get(),
+        }
+    }
+}
+
+/// A pole, or a pair of complex conjugate poles, of an analog low pass
+/// prototype with a cutoff of 1 rad/s.
+#[derive(Clone, Copy, Debug)]
+pub enum Pole {
+    Real(f32),
+    Pair {
+        freq: f32,
+        q: f32,
+    },
+    /// A pair with a pair of zeros on the imaginary axis at `zero` rad/s.
+    NotchPair {
+        freq: f32,
+        q: f32,
+        zero: f32,
+    },
+}
+
+/// Pole pairs of the Bessel prototypes, normalized to -3 dB at 1 rad/s.
+const BESSEL_2: &[(f32, f32)] = &[(1.2720, 0.5773)];
+const BESSEL_3: &[(f32, f32)] = &[(1.4476, 0.6910)];
+const BESSEL_4: &[(f32, f32)] = &[(1.4302, 0.5219), (1.6034, 0.8055)];
+const BESSEL_6: &[(f32, f32)] = &[(1.6039, 0.5103), (1.6892, 0.6112), (1.9047, 1.0233)];
+const BESSEL_8: &[(f32, f32)] = &[
+    (1.7785, 0.5060),
+    (1.8321, 0.5596),
+    (1.9532, 0.7109),
+    (2.1887, 1.2257),
+];
+const BESSEL_12: &[(f32, f32)] = &[
+    (2.0961, 0.5028),
+    (2.1247, 0.5259),
+    (2.1850, 0.5794),
+    (2.2843, 0.6840),
+    (2.4391, 0.9059),
+    (2.6930, 1.5947),
+];
+const BESSEL_16: &[(f32, f32)] = &[
+    (2.3758, 0.5016),
+    (2.3943, 0.5146),
+    (2.4323, 0.5427),
+    (2.4923, 0.5911),
+    (2.5786, 0.6714),
+    (2.6994, 0.8104),
+    (2.8702, 1.0891),
+    (3.1315, 1.9293),
+];
+
+/// The response of the `LowPass` and `HighPass` kinds.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Shape {
+    /// Maximally flat pass band.
+    #[default]
+    Butterworth,
+    /// Two cascaded Butterworth filters, -6 dB at the cutoff so that low and
+    /// high pass sum flat. Odd orders fall back to Butterworth.
+    LinkwitzRiley,
+    /// Maximally flat group delay, for minimal ringing.
+    Bessel,
+    /// Type I with 1 dB of pass band ripple, for a steeper transition.
+    Chebyshev,
+    /// Ripple in both bands, 1 dB in the pass band and a stop band 60 dB
+    /// down, for the steepest transition.
+    Elliptic,
+}
+
+impl Shape {
+    pub const MAX_ID: u32 = 4;
+
+    /// Pass band ripple of the `Chebyshev` and `Elliptic` shapes in dB.
+    pub const RIPPLE: f32 = 1.0;
+
+    /// Stop band attenuation of the `Elliptic` shape in dB.
+    pub const STOP_BAND: f32 = 60.0;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Shape::Butterworth => "BW",
+            Shape::LinkwitzRiley => "LR",
+            Shape::Bessel => "BS",
+            Shape::Chebyshev => "CH",
+            Shape::Elliptic => "EL",
+        }
+    }
+
+    /// Write the poles of the low pass prototype of `order` to `poles`,
+    /// returns the number of poles written.
+    pub fn prototype(&self, order: usize, poles: &mut [Pole]) -> usize {
+        match self {
+            Shape::Butterworth => Self::butterworth(order, poles),
+            Shape::LinkwitzRiley if order.is_multiple_of(2) => {
+                // every pole of the butterworth filter of half the order is
+                // doubled, a double real pole is a pair with a q of 0.5
+                let count = Self::butterworth(order / 2, poles);
+
+                let mut doubled = [Pole::Real(1.0); FilterState::MAX_SECTIONS];
+                let mut index = 0;
+
+                for pole in &poles[..count] {
+                    match *pole {
+                        Pole::Real(freq) => {
+                            doubled[index] = Pole::Pair { freq, q: 0.5 };
+                            index += 1;
+                        }
+                        Pole::Pair { .. } | Pole::NotchPair { .. } => {
+                            doubled[index] = *pole;
+                            doubled[index + 1] = *pole;
+                            index += 2;
+                        }
+                    }
+                }
+
+                poles[..index].copy_from_slice(&doubled[..index]);
+                index
+            }
+            Shape::LinkwitzRiley => Self::butterworth(order, poles),
+            Shape::Bessel => {
+                let (real, pairs) = match order {
+                    1 => (Some(1.0), &[][..]),
+                    2 => (None, BESSEL_2),
+                    3 => (Some(1.3227), BESSEL_3),
+                    4 => (None, BESSEL_4),
+                    6 => (None, BESSEL_6),
+                    8 => (None, BESSEL_8),
+                    12 => (None, BESSEL_12),
+                    _ => (None, BESSEL_16),
+                };
+
+                let mut count = 0;
+
+                if let Some(freq) = real {
+                    poles[count] = Pole::Real(freq);
+                    count += 1;
+                }
+
+                for &(freq, q) in pairs {
+                    poles[count] = Pole::Pair { freq, q };
+                    count += 1;
+                }
+
+                count
+            }
+            Shape::Chebyshev => {
+                let epsilon = f32::sqrt(f32::powf(10.0, Self::RIPPLE / 10.0) - 1.0);
+                let v = f32::asinh(1.0 / epsilon) / order as f32;
+
+                let mut count = 0;
+
+                if !order.is_multiple_of(2) {
+                    poles[count] = Pole::Real(v.sinh());
+                    count += 1;
+                }
+
+                for k in 0..order / 2 {
+                    let theta = (2 * k + 1) as f32 * PI / (2 * order) as f32;
+
+                    let re = v.sinh() * theta.sin();
+                    let im = v.cosh() * theta.cos();
+
+                    let freq = f32::sqrt(re * re + im * im);
+                    let q = freq / (2.0 * re);
+
+                    poles[count] = Pole::Pair { freq, q };
+                    count += 1;
+                }
+
+                count
+            }
+            Shape::Elliptic => Self::elliptic(order, poles),
+        }
+    }
+
+    fn butterworth(order: usize, poles: &mut [Pole]) -> usize {
+        let mut count = 0;
+
+        if !order.is_multiple_of(2) {
+            poles[count] = Pole::Real(1.0);
+            count += 1;
+        }
+
+        for k in 0..order / 2 {
+            // angle of the pole pair from the negative real axis
+            let angle = match order.is_multiple_of(2) {
+                true => (2 * k + 1) as f32 * PI / (2 * order) as f32,
+                false => (k + 1) as f32 * PI / order as f32,
+            };
+
+            let q = 1.0 / (2.0 * angle.cos());
+
+            poles[count] = Pole::Pair { freq: 1.0, q };
+            count += 1;
+        }
+
+        count
+    }
+
+    /// The elliptic prototype, see Orfanidis, "Lecture Notes on Elliptic
+    /// Filter Design" (2006).
+    ///
+    /// The order and both ripples fix the selectivity `k`, the pass band ends
+    /// at 1 rad/s and the stop band starts at `1 / k` rad/s.
+    fn elliptic(order: usize, poles: &mut [Pole]) -> usize {
+        let epsilon_p = f64::sqrt(f64::powf(10.0, Self::RIPPLE as f64 / 10.0) - 1.0);
+        let epsilon_s = f64::sqrt(f64::powf(10.0, Self::STOP_BAND as f64 / 10.0) - 1.0);
+
+        let k1 = epsilon_p / epsilon_s;
+        let k1_prime = f64::sqrt(1.0 - k1 * k1);
+
+        // the zeros of the pairs sit at these points of the quarter period
+        let u = |i: usize| (2 * i + 1) as f64 / order as f64;
+
+        // solve the degree equation for the selectivity
+        let mut k_prime = k1_prime.powi(order as i32);
+
+        for i in 0..order / 2 {
+            k_prime *= sne(Complex::new(u(i), 0.0), k1_prime).re.powi(4);
+        }
+
+        let k = f64::sqrt(1.0 - k_prime * k_prime);
+
+        let v0 = -asne(Complex::new(0.0, 1.0 / epsilon_p), k1).im / order as f64;
+
+        let mut count = 0;
+
+        if !order.is_multiple_of(2) {
+            let pole = sne(Complex::new(0.0, v0), k).im;
+
+            poles[count] = Pole::Real(pole as f32);
+            count += 1;
+        }
+
+        for i in 0..order / 2 {
+            let zero = 1.0 / (k * cde(Complex::new(u(i), 0.0), k).re);
+            let pole = Complex::<f64>::i() * cde(Complex::new(u(i), -v0), k);
+
+            let freq = pole.norm();
+            let q = freq / (-2.0 * pole.re);
+
+            poles[count] = Pole::NotchPair {
+                freq: freq as f32,
+                q: q as f32,
+                zero: zero as f32,
+            };
+            count += 1;
+        }
+
+        count
+    }
+
+    /// Gain at dc of the prototype of `order`, even order Chebyshev and
+    /// elliptic filters start at the bottom of the ripple.
+    pub fn passband_gain(&self, order: usize) -> f32 {
+        match self {
+            Shape::Chebyshev | Shape::Elliptic if order.is_multiple_of(2) => {
+                f32::powf(10.0, -Self::RIPPLE / 20.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Shape::Butterworth => 0,
+            Shape::LinkwitzRiley => 1,
+            Shape::Bessel => 2,
+            Shape::Chebyshev => 3,
+            Shape::Elliptic => 4,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Shape> {
+        match id {
+            0 => Some(Shape::Butterworth),
+            1 => Some(Shape::LinkwitzRiley),
+            2 => Some(Shape::Bessel),
+            3 => Some(Shape::Chebyshev),
+            4 => Some(Shape::Elliptic),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Shape {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Shape::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Shape {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Shape::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Butterworth.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Shape::from_id(plain.round() as u32) {
+            Some(kind) => match kind {
+                Shape::Butterworth => String::from("Butterworth"),
+                Shape::LinkwitzRiley => String::from("Linkwitz-Riley"),
+                Shape::Bessel => String::from("Bessel"),
+                Shape::Chebyshev => String::from("Chebyshev"),
+                Shape::Elliptic => String::from("Elliptic"),
+            },
+            None => {
+                println!("Shape::to_string: invalid id: {}", plain);
+                String::from("Butterworth")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Butterworth" => Shape::Butterworth.get(),
+            "Linkwitz-Riley" => Shape::LinkwitzRiley.get(),
+            "Bessel" => Shape::Bessel.get(),
+            "Chebyshev" => Shape::Chebyshev.get(),
+            "Elliptic" => Shape::Elliptic.get(),
+            _ => Shape::Butterworth.get(),
+        }
+    }
+}
+
+/// The descending Landen sequence of the modulus `k`, which converges to
+/// zero fast enough that a fixed number of steps reaches f64 precision.
+fn landen(k: f64) -> [f64; 8] {
+    let mut moduli = [0.0; 8];
+    let mut k = k;
+
+    for modulus in &mut moduli {
+        let k_prime = f64::sqrt(1.0 - k * k);
+
+        k = (k / (1.0 + k_prime)).powi(2);
+        *modulus = k;
+    }
+
+    moduli
+}
+
+/// Lift `w`, a function value at modulus zero, back up the Landen sequence
+/// of `k`.
+fn ascend(w: Complex<f64>, k: f64) -> Complex<f64> {
+    landen(k)
+        .iter()
+        .rev()
+        .fold(w, |w, &v| w * (1.0 + v) / (w * w * v + 1.0))
+}
+
+/// The jacobian elliptic function `cd(u K, k)`.
+fn cde(u: Complex<f64>, k: f64) -> Complex<f64> {
+    ascend((u * PI_64 / 2.0).cos(), k)
+}
+
+/// The jacobian elliptic function `sn(u K, k)`.
+fn sne(u: Complex<f64>, k: f64) -> Complex<f64> {
+    ascend((u * PI_64 / 2.0).sin(), k)
+}
+
+/// The inverse of `cde`.
+fn acde(w: Complex<f64>, k: f64) -> Complex<f64> {
+    let mut w = w;
+    let mut previous = k;
+
+    for v in landen(k) {
+        let root = (Complex::new(1.0, 0.0) - w * w * previous * previous).sqrt();
+
+        w = w / (root + 1.0) * 2.0 / (1.0 + v);
+        previous = v;
+    }
+
+    w.acos() * 2.0 / PI_64
+}
+
+/// The inverse of `sne`.
+fn asne(w: Complex<f64>, k: f64) -> Complex<f64> {
+    Complex::new(1.0, 0.0) - acde(w, k)
+}
+
+/// Whether a dynamic band pulls its gain down or pushes it up when the
+/// level crosses the threshold.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Direction {
+    #[default]
+    Compress,
+    Expand,
+}
+
+impl Direction {
+    pub const MAX_ID: u32 = 1;
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Direction::Compress => 0,
+            Direction::Expand => 1,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Direction> {
+        match id {
+            0 => Some(Direction::Compress),
+            1 => Some(Direction::Expand),
+            _ => None,
+        }
+    }
+}
+
+impl Param for Direction {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Direction::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Compress.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Direction::from_id(plain.round() as u32) {
+            Some(Direction::Compress) => String::from("Compress"),
+            Some(Direction::Expand) => String::from("Expand"),
+            None => {
+                println!("Direction::to_string: invalid id: {}", plain);
+                String::from("Compress")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Compress" => Direction::Compress.get(),
+            "Expand" => Direction::Expand.get(),
+            _ => Direction::Compress.get(),
+        }
+    }
+}
+
+/// Which part of the stereo signal a band processes.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Placement {
+    #[default]
+    Stereo,
+    Left,
+    Right,
+    Mid,
+    Side,
+}
+
+impl Placement {
+    pub const MAX_ID: u32 = 4;
+
+    pub fn abbreviation(&self) -> &str {
+        match self {
+            Placement::Stereo => "ST",
+            Placement::Left => "L",
+            Placement::Right => "R",
+            Placement::Mid => "M",
+            Placement::Side => "S",
+        }
+    }
+
+    /// Pick the signals the band processes out of a stereo `frame`, returns
+    /// them with their count.
+    pub fn encode(&self, frame: &[f32; 2], channels: usize) -> ([f32; 2], usize) {
+        let [left, right] = *frame;
+
+        match (self, channels) {
+            (Placement::Stereo, _) => (*frame, channels),
+            (Placement::Left, _) => ([left, 0.0], 1),
+            (Placement::Mid, 1) => ([left, 0.0], 1),
+            (_, 1) => ([0.0; 2], 0),
+            (Placement::Right, _) => ([right, 0.0], 1),
+            (Placement::Mid, _) => ([(left + right) / 2.0, 0.0], 1),
+            (Placement::Side, _) => ([(left - right) / 2.0, 0.0], 1),
+        }
+    }
+
+    /// Write the processed `signals` from `encode` back into `frame`.
+    pub fn decode(&self, frame: &mut [f32; 2], channels: usize, signals: &[f32; 2]) {
+        let [left, right] = *frame;
+
+        match (self, channels) {
+            (Placement::Stereo, _) => *frame = *signals,
+            (Placement::Left, _) => frame[0] = signals[0],
+            (Placement::Mid, 1) => frame[0] = signals[0],
+            (_, 1) => {}
+            (Placement::Right, _) => frame[1] = signals[0],
+            (Placement::Mid, _) => {
+                let side = (left - right) / 2.0;
+                *frame = [signals[0] + side, signals[0] - side];
+            }
+            (Placement::Side, _) => {
+                let mid = (left + right) / 2.0;
+                *frame = [mid + signals[0], mid - signals[0]];
+            }
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Placement::Stereo => 0,
+            Placement::Left => 1,
+            Placement::Right => 2,
+            Placement::Mid => 3,
+            Placement::Side => 4,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Placement> {
+        match id {
+            0 => Some(Placement::Stereo),
+            1 => Some(Placement::Left),
+            2 => Some(Placement::Right),
+            3 => Some(Placement::Mid),
+            4 => Some(Placement::Side),
+            _ => None,
+        }
+    }
+
+    pub fn next(&self) -> Placement {
+        let id = self.id();
+        let next_id = (id + 1) % (Self::MAX_ID + 1);
+        Placement::from_id(next_id).unwrap()
+    }
+}
+
+impl Param for Placement {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Placement::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Stereo.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Placement::from_id(plain.round() as u32) {
+            Some(kind) => match kind {
+                Placement::Stereo => String::from("Stereo"),
+                Placement::Left => String::from("Left"),
+                Placement::Right => String::from("Right"),
+                Placement::Mid => String::from("Mid"),
+                Placement::Side => String::from("Side"),
+            },
+            None => {
+                println!("Placement::to_string: invalid id: {}", plain);
+                String::from("Stereo")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Stereo" => Placement::Stereo.get(),
+            "Left" => Placement::Left.get(),
+            "Right" => Placement::Right.get(),
+            "Mid" => Placement::Mid.get(),
+            "Side" => Placement::Side.get(),
+            _ => Placement::Stereo.get(),
+        }
+    }
+}
+
+/// Which signal a dynamic band listens to.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Detection {
+    /// The signal entering the band.
+    #[default]
+    Internal,
+    /// The sidechain input, falls back to `Internal` when it isn't connected.
+    External,
+}
+
+impl Detection {
+    pub const MAX_ID: u32 = 1;
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Detection::Internal => 0,
+            Detection::External => 1,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Detection> {
+        match id {
+            0 => Some(Detection::Internal),
+            1 => Some(Detection::External),
+            _ => None,
+        }
+    }
+}
+
+impl Param for Detection {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Detection::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Internal.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Detection::from_id(plain.round() as u32) {
+            Some(Detection::Internal) => String::from("Internal"),
+            Some(Detection::External) => String::from("External"),
+            None => {
+                println!("Detection::to_string: invalid id: {}", plain);
+                String::from("Internal")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Internal" => Detection::Internal.get(),
+            "External" => Detection::External.get(),
+            _ => Detection::Internal.get(),
+        }
+    }
+}
+
+/// How the bell, shelf and notch filters are turned into digital filters.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum FilterDesign {
+    /// The bilinear transform, as in the RBJ audio EQ cookbook.
+    #[default]
+    Rbj,
+    /// Matched magnitude, follows the analog response up to nyquist.
+    Matched,
+}
+
+impl FilterDesign {
+    pub const MAX_ID: u32 = 1;
+
+    pub fn id(&self) -> u32 {
+        match self {
+            FilterDesign::Rbj => 0,
+            FilterDesign::Matched => 1,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<FilterDesign> {
+        match id {
+            0 => Some(FilterDesign::Rbj),
+            1 => Some(FilterDesign::Matched),
+            _ => None,
+        }
+    }
+}
+
+impl Param for FilterDesign {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = FilterDesign::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Rbj.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match FilterDesign::from_id(plain.round() as u32) {
+            Some(FilterDesign::Rbj) => String::from("RBJ"),
+            Some(FilterDesign::Matched) => String::from("Matched"),
+            None => {
+                println!("FilterDesign::to_string: invalid id: {}", plain);
+                String::from("RBJ")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "RBJ" => FilterDesign::Rbj.get(),
+            "Matched" => FilterDesign::Matched.get(),
+            _ => FilterDesign::Rbj.get(),
+        }
+    }
+}
+
+/// The precision the band sections run at.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Precision {
+    #[default]
+    Single,
+    /// Coefficients and state in f64, for low frequency and high Q bands at
+    /// high sample rates.
+    Double,
+}
+
+impl Precision {
+    pub const MAX_ID: u32 = 1;
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Precision::Single => 0,
+            Precision::Double => 1,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Precision> {
+        match id {
+            0 => Some(Precision::Single),
+            1 => Some(Precision::Double),
+            _ => None,
+        }
+    }
+}
+
+impl Param for Precision {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Precision::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Single.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Precision::from_id(plain.round() as u32) {
+            Some(Precision::Single) => String::from("32-bit"),
+            Some(Precision::Double) => String::from("64-bit"),
+            None => {
+                println!("Precision::to_string: invalid id: {}", plain);
+                String::from("32-bit")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "32-bit" => Precision::Single.get(),
+            "64-bit" => Precision::Double.get(),
+            _ => Precision::Single.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A second order analog prototype `b(s) / a(s)`, coefficients from the
+    /// highest power down, with `s` normalized to the design frequency.
+    struct Prototype {
+        b: [f64; 3],
+        a: [f64; 3],
+    }
+
+    impl Prototype {
+        fn response(&self, s: Complex<f64>) -> Complex<f64> {
+            let eval = |p: [f64; 3]| (s * p[0] + p[1]) * s + p[2];
+            eval(self.b) / eval(self.a)
+        }
+
+        /// The bilinear transform prewarped at `w0`, as direct form
+        /// coefficients `[b0, b1, b2, a0, a1, a2]`.
+        fn bilinear(&self, w0: f64) -> [f64; 6] {
+            let k = f64::tan(w0 / 2.0);
+            let z = |p: [f64; 3]| {
+                let (p2, p1, p0) = (p[0] / (k * k), p[1] / k, p[2]);
+                [p2 + p1 + p0, 2.0 * (p0 - p2), p2 - p1 + p0]
+            };
+
+            let [b0, b1, b2] = z(self.b);
+            let [a0, a1, a2] = z(self.a);
+            [b0, b1, b2, a0, a1, a2]
+        }
+    }
+
+    fn prototypes() -> Vec<(&'static str, Prototype)> {
+        let a = f64::powf(10.0, 9.0 / 40.0);
+        let q = 3.0;
+
+        vec![
+            (
+                "low pass",
+                Prototype {
+                    b: [0.0, 0.0, 1.0],
+                    a: [1.0, std::f64::consts::SQRT_2, 1.0],
+                },
+            ),
+            (
+                "high pass",
+                Prototype {
+                    b: [1.0, 0.0, 0.0],
+                    a: [1.0, 1.0 / q, 1.0],
+                },
+            ),
+            (
+                "band pass",
+                Prototype {
+                    b: [0.0, 1.0 / q, 0.0],
+                    a: [1.0, 1.0 / q, 1.0],
+                },
+            ),
+            (
+                "notch",
+                Prototype {
+                    b: [1.0, 0.0, 1.0],
+                    a: [1.0, 1.0 / q, 1.0],
+                },
+            ),
+            (
+                "peak",
+                Prototype {
+                    b: [1.0, a / q, 1.0],
+                    a: [1.0, 1.0 / (a * q), 1.0],
+                },
+            ),
+            (
+                "high shelf",
+                Prototype {
+                    b: [a * a, a * a.sqrt() / q, a],
+                    a: [1.0, a.sqrt() / q, a],
+                },
+            ),
+        ]
+    }
+
+    /// Design frequencies from the low end at a high rate to close to nyquist.
+    const DESIGNS: [(f64, f64); 4] = [
+        (20.0, 192000.0),
+        (1000.0, 48000.0),
+        (15000.0, 44100.0),
+        (21000.0, 44100.0),
+    ];
+
+    /// Frequencies as fractions of nyquist, the last one right below it.
+    const PROBES: [f64; 6] = [0.001, 0.01, 0.1, 0.5, 0.9, 0.99];
+
+    fn assert_close(actual: Complex<f64>, expected: Complex<f64>, what: &str) {
+        let error = (actual - expected).norm();
+        let tolerance = 1.0e-4 * f64::max(expected.norm(), 1.0);
+
+        assert!(error <= tolerance, "{what}: {actual} != {expected}");
+    }
+
+    #[test]
+    fn set_matches_the_analog_prototype() {
+        for (name, prototype) in prototypes() {
+            for (freq, sample_rate) in DESIGNS {
+                let w0 = 2.0 * PI_64 * freq / sample_rate;
+                let [b0, b1, b2, a0, a1, a2] = prototype.bilinear(w0);
+
+                let mut biquad = Biquad::default();
+                biquad.set(b0, b1, b2, a0, a1, a2);
+
+                for probe in PROBES {
+                    let w = probe * PI_64;
+
+                    // the bilinear transform maps `w` to the analog frequency
+                    // `tan(w / 2)`, relative to the prewarped design frequency
+                    let s = Complex::new(0.0, f64::tan(w / 2.0) / f64::tan(w0 / 2.0));
+
+                    let h = biquad.response(w as f32);
+                    let h = Complex::new(h.re as f64, h.im as f64);
+
+                    let what = format!("{name} at {freq} Hz / {sample_rate} Hz, w = {probe} pi");
+                    assert_close(h, prototype.response(s), &what);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn set_svf_matches_the_analog_prototype() {
+        let (k, m0, m1, m2) = (0.5, 1.0, 0.5 * 3.0, 0.0);
+
+        // `(m0 (s^2 + k s + 1) + m1 s + m2) / (s^2 + k s + 1)`
+        let prototype = Prototype {
+            b: [m0, m0 * k + m1, m0 + m2],
+            a: [1.0, k, 1.0],
+        };
+
+        for (freq, sample_rate) in DESIGNS {
+            let w0 = 2.0 * PI_64 * freq / sample_rate;
+            let g = f64::tan(w0 / 2.0);
+
+            let mut biquad = Biquad::default();
+            biquad.set_svf(g, k, m0, m1, m2);
+
+            for probe in PROBES {
+                let w = probe * PI_64;
+                let s = Complex::new(0.0, f64::tan(w / 2.0) / g);
+
+                let h = biquad.response(w as f32);
+                let h = Complex::new(h.re as f64, h.im as f64);
+
+                let what = format!("svf at {freq} Hz / {sample_rate} Hz, w = {probe} pi");
+                assert_close(h, prototype.response(s), &what);
+            }
+        }
+    }
+
+    /// Run a sine through both precisions of `biquad` and measure the gain
+    /// once it has settled.
+    fn measure(biquad: &mut Biquad, w: f64) -> [f64; 2] {
+        biquad.reset();
+
+        let settle = 20000;
+        let length = 20000;
+
+        let mut power = [0.0; 2];
+        let mut reference = 0.0;
+
+        for n in 0..settle + length {
+            let x = f64::sin(w * n as f64);
+            let single = biquad.single.process([x as f32; 2])[0] as f64;
+            let double = biquad.double.process([x; 2])[0];
+
+            if n >= settle {
+                power[0] += single * single;
+                power[1] += double * double;
+                reference += x * x;
+            }
+        }
+
+        power.map(|power| f64::sqrt(power / reference))
+    }
+
+    #[test]
+    fn processing_matches_the_response() {
+        for (name, prototype) in prototypes() {
+            let (freq, sample_rate) = (1000.0, 48000.0);
+
+            let w0 = 2.0 * PI_64 * freq / sample_rate;
+            let [b0, b1, b2, a0, a1, a2] = prototype.bilinear(w0);
+
+            let mut biquad = Biquad::default();
+            biquad.set(b0, b1, b2, a0, a1, a2);
+
+            for probe in [0.02, 0.0417, 0.3, 0.9] {
+                let w = probe * PI_64;
+                let expected = biquad.response(w as f32).norm() as f64;
+
+                for (gain, precision) in measure(&mut biquad, w).into_iter().zip(["f32", "f64"]) {
+                    let error = (gain - expected).abs();
+
+                    assert!(
+                        error <= 1.0e-3 * f64::max(expected, 1.0),
+                        "{name} in {precision} at w = {probe} pi: {gain} != {expected}",
+                    );
+                }
+            }
+        }
+    }
+
+    /// Where the stop band of the elliptic prototype starts, in multiples of
+    /// the cutoff, for every slope above 6 dB.
+    const ELLIPTIC_STOP_BANDS: [(Slope, f32); 7] = [
+        (Slope::Db12, 22.18),
+        (Slope::Db18, 5.03),
+        (Slope::Db24, 2.47),
+        (Slope::Db36, 1.35),
+        (Slope::Db48, 1.11),
+        (Slope::Db72, 1.02),
+        (Slope::Db96, 1.002),
+    ];
+
+    #[test]
+    fn elliptic_meets_both_ripples() {
+        let (cutoff, sample_rate) = (1000.0, 192000.0);
+
+        for (slope, stop_band) in ELLIPTIC_STOP_BANDS {
+            for kind in [FilterKind::LowPass, FilterKind::HighPass] {
+                let mut filter = Filter::new(0, 1);
+                filter.set_kind(kind);
+                filter.slope = slope;
+                filter.shape = Shape::Elliptic;
+                *filter.freq = cutoff;
+
+                let mut state = FilterState::default();
+                state.set_params(&filter, FilterDesign::Rbj, sample_rate);
+
+                let high_pass = matches!(kind, FilterKind::HighPass);
+
+                // log spaced over three octaves on either side of the edges
+                let points = (0..=300).map(|i| f32::powf(2.0, i as f32 / 100.0));
+
+                for ratio in points {
+                    let (pass, stop) = match high_pass {
+                        true => (cutoff * ratio, cutoff / (stop_band * ratio * 1.001)),
+                        false => (cutoff / ratio, cutoff * stop_band * ratio * 1.001),
+                    };
+
+                    let what = format!("{} {}", kind.abbreviation(), slope.abbreviation());
+
+                    let gain = state.gain_at(pass, sample_rate);
+                    assert!(
+                        (-Shape::RIPPLE - 0.05..=0.05).contains(&gain),
+                        "{what}: {gain} dB at {pass} Hz",
+                    );
+
+                    // nothing to measure past nyquist
+                    if stop > sample_rate / 2.0 {
+                        continue;
+                    }
+
+                    let gain = state.gain_at(stop, sample_rate);
+                    assert!(
+                        gain <= -Shape::STOP_BAND + 0.1,
+                        "{what}: {gain} dB at {stop} Hz",
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn kind_keeps_its_normalized_values() {
+        let mut filter = Filter::new(0, 1);
+
+        // the normalized values from before the extra kinds were added
+        for id in 0..=7 {
+            let plain = filter.kind.plain(id as f32 / 7.0);
+            filter.kind.set(plain);
+
+            assert_eq!(filter.kind().id(), id);
+        }
+
+        for id in 0..=FilterKind::MAX_ID {
+            let kind = FilterKind::from_id(id).unwrap();
+            filter.set_kind(kind);
+
+            assert_eq!(filter.kind().id(), id);
+            assert!(filter.kind.id() <= FilterKind::PARAM_MAX_ID);
+        }
+    }
+}
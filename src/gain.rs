@@ -0,0 +1,159 @@
+use std::f64::consts::PI;
+
+use crate::filter::{Biquad, Smoothed};
+
+/// The global gain around the band chain, input trim and polarity before it
+/// and auto gain and output gain after it.
+#[derive(Default)]
+pub struct GainStage {
+    /// Input trim in dB.
+    pub input: Smoothed,
+    /// Output gain in dB.
+    pub output: Smoothed,
+    /// Gain in dB making up for the loudness change of the band chain.
+    pub compensation: Smoothed,
+    /// How far the polarity is flipped, from 0 to 1, ramped through silence
+    /// so flipping it doesn't click.
+    pub invert: Smoothed,
+    pub ramp: u32,
+    /// Ramp of the compensation and the polarity, which unlike the gains
+    /// never step, as they change without the user moving anything.
+    pub fade: u32,
+    pub auto_gain: bool,
+    /// Freezes the loudness measurement and the compensation, so auditioning
+    /// a band doesn't throw them off.
+    pub hold: bool,
+    /// K-weighting before and after the band chain, see ITU-R BS.1770.
+    weighting: [[Biquad; 2]; 2],
+    /// Weighted mean square before and after the band chain.
+    loudness: [f32; 2],
+    coefficient: f32,
+    sample_rate: f32,
+}
+
+impl GainStage {
+    /// Time constant of the loudness measurement, the short term window of
+    /// EBU R 128.
+    const LOUDNESS_TIME: f32 = 3.0;
+
+    /// Mean square below which the loudness isn't trusted, about -80 dB.
+    const SILENCE: f32 = 1.0e-8;
+
+    /// Limit of the compensation in dB.
+    const COMPENSATION_MAX: f32 = 24.0;
+
+    /// Shortest ramp of the compensation and the polarity in seconds.
+    const FADE_MIN: f32 = 0.02;
+
+    pub fn set_params(
+        &mut self,
+        input: f32,
+        output: f32,
+        invert: bool,
+        auto_gain: bool,
+        ramp_ms: f32,
+        sample_rate: f32,
+    ) {
+        self.ramp = (ramp_ms * sample_rate / 1000.0).round() as u32;
+        self.fade = u32::max(self.ramp, (Self::FADE_MIN * sample_rate).round() as u32);
+
+        self.input.set_target(input, self.ramp);
+        self.output.set_target(output, self.ramp);
+        self.invert.set_target(invert as u32 as f32, self.fade);
+        self.auto_gain = auto_gain;
+
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            self.coefficient = f32::exp(-1.0 / (Self::LOUDNESS_TIME * sample_rate));
+
+            for weighting in self.weighting.iter_mut() {
+                Self::set_weighting(weighting, sample_rate);
+            }
+        }
+    }
+
+    fn set_weighting(weighting: &mut [Biquad; 2], sample_rate: f32) {
+        let sample_rate = sample_rate as f64;
+
+        // the head modelling high shelf, +4 dB from about 1.7 kHz
+        let a = f64::powf(10.0, 4.0 / 40.0);
+        let g = f64::tan(PI * 1681.97 / sample_rate) * a.sqrt();
+        let k = 1.0 / 0.7072;
+
+        weighting[0].set_svf(g, k, a * a, k * (1.0 - a) * a, 1.0 - a * a);
+
+        // and the revised low frequency b curve, a high pass at 38 Hz
+        weighting[1].set_high_pass_2(2.0 * PI * 38.135 / sample_rate, 0.5003);
+    }
+
+    /// Apply the input trim and polarity to `frames` and measure their
+    /// loudness going into the band chain.
+    pub fn process_input(&mut self, frames: &mut [[f32; 2]]) {
+        Self::apply(&mut self.input, frames, db_to_gain);
+        Self::apply(&mut self.invert, frames, |invert| 1.0 - 2.0 * invert);
+
+        if !self.hold {
+            self.measure(0, frames);
+        }
+    }
+
+    /// Measure the loudness of `frames` coming out of the band chain and
+    /// apply the compensation and output gain.
+    pub fn process_output(&mut self, frames: &mut [[f32; 2]]) {
+        if !self.hold {
+            self.measure(1, frames);
+            self.update_compensation();
+        }
+
+        Self::apply(&mut self.compensation, frames, db_to_gain);
+        Self::apply(&mut self.output, frames, db_to_gain);
+    }
+
+    fn update_compensation(&mut self) {
+        let [before, after] = self.loudness;
+
+        if !self.auto_gain {
+            self.compensation.set_target(0.0, self.fade);
+        } else if before > Self::SILENCE && after > Self::SILENCE {
+            let compensation = 10.0 * f32::log10(before / after);
+            let max = Self::COMPENSATION_MAX;
+
+            self.compensation
+                .set_target(compensation.clamp(-max, max), self.fade);
+        }
+    }
+
+    fn measure(&mut self, index: usize, frames: &[[f32; 2]]) {
+        let [shelf, high_pass] = &mut self.weighting[index];
+        let loudness = &mut self.loudness[index];
+
+        for frame in frames {
+            let weighted = high_pass.process(shelf.process(*frame));
+            let power = weighted[0] * weighted[0] + weighted[1] * weighted[1];
+
+            *loudness = power + self.coefficient * (*loudness - power);
+        }
+    }
+
+    /// Scale `frames` by the `gain` of `value`, ramping while it's smoothing.
+    fn apply(value: &mut Smoothed, frames: &mut [[f32; 2]], gain: impl Fn(f32) -> f32) {
+        if !value.is_smoothing() {
+            let gain = gain(value.current);
+
+            for frame in frames.iter_mut() {
+                *frame = frame.map(|sample| sample * gain);
+            }
+
+            return;
+        }
+
+        for frame in frames.iter_mut() {
+            let gain = gain(value.advance(1));
+            *frame = frame.map(|sample| sample * gain);
+        }
+    }
+}
+
+fn db_to_gain(db: f32) -> f32 {
+    f32::powf(10.0, db / 20.0)
+}
@@ -1,36 +1,115 @@
 use std::{
-    f32::consts::PI,
-    sync::Arc,
+    ops::Range,
+    sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
-use filter::{Filter, FilterState};
-use num::Complex;
+use analyzer::{
+    AnalyzerFeed, AnalyzerSettings, AnalyzerThread, FftSize, Smoothing, Spectra, StereoMode, Tilt,
+    Traces,
+};
+use bands::Bands;
+use delay::Delay;
+use filter::{Detection, Filter, FilterDesign, Placement, Precision};
+use gain::GainStage;
+use linear_phase::{LinearPhase, PhaseMode, Resolution};
+use lock_free::Reader;
+use matching::{Capture, MatchEq};
 use ori_vst::prelude::*;
-use realfft::{RealFftPlanner, RealToComplex};
-
+use oversampling::{Oversampler, Oversampling};
+
+mod analyzer;
+mod bands;
+#[cfg(feature = "bench")]
+pub mod bench;
+mod delay;
+mod dynamics;
 mod filter;
+mod gain;
+mod linear_phase;
+mod lock_free;
+mod matching;
+mod oversampling;
 
 #[derive(Params)]
 pub struct FreeqParams {
     #[param(group)]
     filters: [Filter; 10],
+    smoothing: Float,
+    design: FilterDesign,
+    /// Changing it restarts the plugin, as the latency changes.
+    phase_mode: PhaseMode,
+    /// Changing it restarts the plugin, as the latency changes.
+    resolution: Resolution,
+    /// Changing it restarts the plugin, as the latency changes.
+    oversampling: Oversampling,
+    /// Used instead of `oversampling` when rendering offline, if higher.
+    offline_oversampling: Oversampling,
+    /// The precision of the band sections.
+    precision: Precision,
+    input_gain: Float,
+    output_gain: Float,
+    invert: Bool,
+    /// Compensates the loudness change of the bands, so comparing with the
+    /// bypassed signal isn't biased by level.
+    auto_gain: Bool,
+    match_amount: Float,
+    /// Width in octaves the spectra are smoothed over before matching.
+    match_smoothing: Float,
+    analyzer_size: FftSize,
+    /// Whether the lows and highs get FFT sizes of their own.
+    analyzer_resolution: analyzer::Resolution,
+    /// How much of an analyzer frame is shared with the next.
+    analyzer_overlap: Float,
+    analyzer_window: analyzer::Window,
+    /// Time constant the analyzer averages over.
+    analyzer_decay: Float,
+    analyzer_range: Float,
+    /// Level at the bottom of the analyzer.
+    analyzer_floor: Float,
+    analyzer_smoothing: Smoothing,
+    /// Slope the analyzer is tilted by, so pink noise or music reads flat.
+    analyzer_tilt: Tilt,
+    /// Shows the peaks of the analyzer, held and then falling.
+    analyzer_peak: Bool,
+    analyzer_hold: Float,
+    analyzer_fall: Float,
+    /// Shows the highest levels of the analyzer since it was cleared.
+    analyzer_max: Bool,
+    analyzer_traces: Traces,
+    analyzer_stereo: StereoMode,
 }
 
 vst3!(Freeq);
 
 pub struct Freeq {
     params: FreeqParams,
-    filters: [[FilterState; 10]; 2],
-    fft: Arc<dyn RealToComplex<f32>>,
-    prev_input: f32,
-    prev_output: f32,
-    buffer_a: Vec<f32>,
-    buffer_b: Vec<f32>,
-    complex: Vec<Complex<f32>>,
-    scratch: Vec<Complex<f32>>,
-    spectrum: Vec<f32>,
-    current: usize,
+    bands: Bands,
+    gain: GainStage,
+    /// The band soloed from the ui, see `Bands::set_solo`.
+    solo: Option<usize>,
+    linear_phase: Option<LinearPhase>,
+    oversampler: Oversampler,
+    /// The `latency_params` the host was last told about.
+    reported_params: [u32; 3],
+    /// The parameter changes of the current block with their arrival index,
+    /// see `queue_changes`.
+    changes: Vec<(usize, ParamChange)>,
+    next_change: usize,
+    frames: Vec<[f32; 2]>,
+    keys: Vec<[f32; 2]>,
+    oversampled: Vec<[f32; 2]>,
+    oversampled_keys: Vec<[f32; 2]>,
+    /// The input of the band chain delayed by the latency, which a soloed
+    /// band is auditioned from.
+    dry: Vec<[f32; 2]>,
+    dry_delay: Delay,
+    analyzer: AnalyzerFeed,
+    /// The analyzer spectra, read by the ui.
+    spectra: Reader<Spectra>,
+    /// Shared with the analyzer thread, which captures the spectra.
+    matching: Arc<Mutex<MatchEq>>,
+    _analyzer_thread: AnalyzerThread,
     sample_rate: f32,
 }
 
@@ -46,12 +125,16 @@ impl VstPlugin for Freeq {
         }
     }
 
-    fn layout(_inputs: &[u32], _outputs: &[u32]) -> Option<AudioLayout> {
-        let layout = AudioLayout::new()
-            .with_input(AudioPort::new(2))
-            .with_output(AudioPort::new(2));
+    fn layout(inputs: &[u32], _outputs: &[u32]) -> Option<AudioLayout> {
+        let mut layout = AudioLayout::new().with_input(AudioPort::new(2));
 
-        Some(layout)
+        // the sidechain is optional, only declare it when the host offers a
+        // second input bus
+        if inputs.len() > 1 {
+            layout = layout.with_input(AudioPort::new(2));
+        }
+
+        Some(layout.with_output(AudioPort::new(2)))
     }
 
     fn window() -> Window {
@@ -59,6 +142,9 @@ impl VstPlugin for Freeq {
     }
 
     fn new() -> Self {
+        let matching = Arc::new(Mutex::new(MatchEq::default()));
+        let (analyzer, spectra, analyzer_thread) = analyzer::spawn(matching.clone());
+
         Self {
             params: FreeqParams {
                 filters: [
@@ -73,17 +159,58 @@ impl VstPlugin for Freeq {
                     Filter::new(8, 10),
                     Filter::new(9, 10),
                 ],
+                smoothing: Float::new(20.0, 0.0..=200.0).name("Smoothing (ms)"),
+                design: FilterDesign::Rbj,
+                phase_mode: PhaseMode::Minimum,
+                resolution: Resolution::Medium,
+                oversampling: Oversampling::X1,
+                offline_oversampling: Oversampling::X1,
+                precision: Precision::Single,
+                input_gain: Float::new(0.0, -24.0..=24.0)
+                    .name("Input Gain (dB)")
+                    .automate(),
+                output_gain: Float::new(0.0, -24.0..=24.0)
+                    .name("Output Gain (dB)")
+                    .automate(),
+                invert: Bool::new(false).name("Invert Polarity").automate(),
+                auto_gain: Bool::new(false).name("Auto Gain"),
+                match_amount: Float::new(100.0, 0.0..=100.0).name("Match Amount (%)"),
+                match_smoothing: Float::new(1.0 / 3.0, 1.0 / 12.0..=2.0)
+                    .name("Match Smoothing (oct)"),
+                analyzer_size: FftSize::K4,
+                analyzer_resolution: analyzer::Resolution::Single,
+                analyzer_overlap: Float::new(50.0, 0.0..=95.0).name("Analyzer Overlap (%)"),
+                analyzer_window: analyzer::Window::Hann,
+                analyzer_decay: Float::new(100.0, 0.0..=2000.0).name("Analyzer Decay (ms)"),
+                analyzer_range: Float::new(100.0, 20.0..=160.0).name("Analyzer Range (dB)"),
+                analyzer_floor: Float::new(-90.0, -160.0..=0.0).name("Analyzer Floor (dB)"),
+                analyzer_smoothing: Smoothing::Off,
+                analyzer_tilt: Tilt::Db3,
+                analyzer_peak: Bool::new(false).name("Analyzer Peak"),
+                analyzer_hold: Float::new(1000.0, 0.0..=10000.0).name("Analyzer Peak Hold (ms)"),
+                analyzer_fall: Float::new(12.0, 3.0..=96.0).name("Analyzer Peak Fall (dB/s)"),
+                analyzer_max: Bool::new(false).name("Analyzer Max"),
+                analyzer_traces: Traces::Post,
+                analyzer_stereo: StereoMode::Sum,
             },
-            filters: Default::default(),
-            fft: RealFftPlanner::new().plan_fft_forward(Self::FFT_SIZE),
-            prev_input: 0.0,
-            prev_output: 0.0,
-            buffer_a: vec![0.0; Self::FFT_SIZE],
-            buffer_b: vec![0.0; Self::FFT_SIZE],
-            complex: vec![Complex::new(0.0, 0.0); Self::FFT_SIZE / 2 + 1],
-            scratch: vec![Complex::new(0.0, 0.0); Self::FFT_SIZE / 2 + 1],
-            spectrum: vec![0.0; Self::FFT_SIZE / 2 + 1],
-            current: 0,
+            bands: Bands::default(),
+            gain: GainStage::default(),
+            solo: None,
+            linear_phase: None,
+            oversampler: Oversampler::new(Oversampling::X1),
+            reported_params: [0; 3],
+            changes: Vec::with_capacity(Self::CHANGE_CAPACITY),
+            next_change: 0,
+            frames: Vec::with_capacity(Self::BLOCK_CAPACITY),
+            keys: Vec::with_capacity(Self::BLOCK_CAPACITY),
+            oversampled: Vec::new(),
+            oversampled_keys: Vec::new(),
+            dry: Vec::with_capacity(Self::BLOCK_CAPACITY),
+            dry_delay: Delay::default(),
+            analyzer,
+            spectra,
+            matching,
+            _analyzer_thread: analyzer_thread,
             sample_rate: 44100.0,
         }
     }
@@ -100,63 +227,171 @@ impl VstPlugin for Freeq {
             filters.push(filter);
         }
 
-        let filters = hstack(filters);
+        let options = hstack![hstack(filters), match_options(self), analyzer_options(self)];
 
-        vstack![flex(curve_view(self)), filters].align(Align::Start)
+        vstack![flex(curve_view(self)), options].align(Align::Start)
     }
 
     fn activate(&mut self, _audio_layout: &AudioLayout, buffer_layout: &BufferLayout) -> Activate {
         self.sample_rate = buffer_layout.sample_rate;
 
-        Activate::new()
+        self.linear_phase = match self.params.phase_mode {
+            PhaseMode::Minimum => None,
+            PhaseMode::Linear => Some(LinearPhase::new(
+                self.params.resolution,
+                self.params.filters.len(),
+                buffer_layout.sample_rate,
+            )),
+        };
+
+        let oversampling = self.oversampling(buffer_layout.offline);
+        self.reported_params = self.latency_params(buffer_layout.offline);
+
+        // the linear phase kernels are sampled from the ideal response, so
+        // there's no cramping for oversampling to fix
+        self.oversampler = match self.linear_phase {
+            Some(_) => Oversampler::new(Oversampling::X1),
+            None => Oversampler::new(oversampling),
+        };
+
+        let factor = self.oversampler.factor();
+        self.oversampled.reserve(Self::BLOCK_CAPACITY * factor);
+        self.oversampled_keys.reserve(Self::BLOCK_CAPACITY * factor);
+
+        let latency = match self.linear_phase {
+            Some(ref linear_phase) => linear_phase.latency(),
+            None => self.oversampler.latency(),
+        };
+
+        self.dry_delay = Delay::new(latency);
+
+        Activate::new().latency(latency as u32)
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer<'_>,
-        _aux_buffers: &mut [Buffer<'_>],
+        aux_buffers: &mut [Buffer<'_>],
         layout: BufferLayout,
     ) -> Process {
-        for (i, filter) in self.params.filters.iter().enumerate() {
-            for filters in self.filters.iter_mut() {
-                filters[i].set_params(filter, layout.sample_rate)
+        // gather the block, so every band can run over all of it at once
+        let mut channels = 1;
+        self.frames.clear();
+
+        for mut samples in buffer.iter_samples() {
+            let left = samples.next().map_or(0.0, |sample| *sample);
+            let right = samples.next().map(|sample| *sample);
+
+            if right.is_some() {
+                channels = 2;
             }
+
+            self.frames.push([left, right.unwrap_or(0.0)]);
         }
 
-        for samples in buffer.iter_samples() {
-            let mut average = 0.0;
+        self.queue_changes(buffer);
 
-            for (sample, filters) in samples.zip(self.filters.iter_mut()) {
-                for filter in filters.iter_mut() {
-                    if !filter.enabled {
-                        continue;
-                    }
+        // the state at the start of the block applies from its first sample
+        self.apply_changes(0);
+        self.update_params(layout.sample_rate);
 
-                    *sample = filter.process(*sample);
-                }
+        self.keys.clear();
 
-                average += *sample;
+        if let Some(sidechain) = aux_buffers.first_mut() {
+            for mut key in sidechain.iter_samples() {
+                let left = key.next().map_or(0.0, |sample| *sample);
+                let right = key.next().map_or(left, |sample| *sample);
+
+                self.keys.push([left, right]);
             }
 
-            average /= buffer.channels() as f32;
+            self.keys.resize(self.frames.len(), [0.0; 2]);
+        }
+
+        // the analyzer thread does the rest
+        let settings = AnalyzerSettings {
+            size: self.params.analyzer_size,
+            resolution: self.params.analyzer_resolution,
+            overlap: *self.params.analyzer_overlap / 100.0,
+            window: self.params.analyzer_window,
+            decay_ms: *self.params.analyzer_decay,
+            smoothing: self.params.analyzer_smoothing,
+            stereo: self.params.analyzer_stereo,
+            traces: self.params.analyzer_traces,
+            peak_hold_ms: *self.params.analyzer_hold,
+            peak_fall: *self.params.analyzer_fall,
+        };
+
+        let sidechain = !self.keys.is_empty();
 
-            // apply high-pass filter to remove DC offset
-            let high_pass = 0.999 * (self.prev_output + average - self.prev_input);
-            self.prev_input = average;
-            self.prev_output = high_pass;
+        self.analyzer
+            .set_settings(settings, layout.sample_rate, channels, sidechain);
+        self.analyzer.push_input(&self.frames);
 
-            let i_a = self.current;
-            let i_b = (self.current + self.buffer_a.len() / 2) % self.buffer_a.len();
+        if sidechain {
+            self.analyzer.push_keys(&self.keys);
+        }
 
-            self.buffer_a[i_a] = high_pass;
-            self.buffer_b[i_b] = high_pass;
-            self.current += 1;
-            self.current %= self.buffer_a.len();
+        let mut start = 0;
 
-            if self.current == 0 {
-                self.compute_fft(false);
-            } else if self.current == self.buffer_a.len() / 2 {
-                self.compute_fft(true);
+        while start < self.frames.len() {
+            if self.apply_changes(start) {
+                self.update_params(layout.sample_rate);
+            }
+
+            // run up to the next parameter change
+            let end = match self.changes.get(self.next_change) {
+                Some((_, change)) => change.offset,
+                None => self.frames.len(),
+            };
+
+            self.gain.process_input(&mut self.frames[start..end]);
+
+            // the dry signal is delayed like the chain, so the audition
+            // lines up with the latency the host compensates for
+            self.dry.clear();
+            self.dry.extend_from_slice(&self.frames[start..end]);
+            self.dry_delay.process(&mut self.dry);
+
+            // the chain keeps running while soloing, so it doesn't come back
+            // with stale state
+            self.process_block(start..end, channels);
+
+            if self.bands.solo.is_some() {
+                self.bands.audition(&mut self.dry, channels);
+                self.frames[start..end].copy_from_slice(&self.dry);
+            }
+
+            self.gain.process_output(&mut self.frames[start..end]);
+
+            start = end;
+        }
+
+        self.analyzer.push_output(&self.frames);
+
+        // the latency is only reported on activation, so ask the host to
+        // restart us when a parameter it depends on changes
+        let latency_params = self.latency_params(layout.offline);
+
+        if latency_params != self.reported_params {
+            self.reported_params = latency_params;
+
+            // the output of a block that restarts is dropped, so it goes out
+            // silent rather than as whatever the buffer still holds
+            for samples in buffer.iter_samples() {
+                for sample in samples {
+                    *sample = 0.0;
+                }
+            }
+
+            return Process::Restart;
+        }
+
+        for (index, samples) in buffer.iter_samples().enumerate() {
+            let frame = self.frames[index];
+
+            for (sample, value) in samples.zip(frame) {
+                *sample = value;
             }
         }
 
@@ -165,56 +400,239 @@ impl VstPlugin for Freeq {
 }
 
 impl Freeq {
-    pub const FFT_SIZE: usize = 4096;
+    /// Block size the scratch buffers are allocated for up front, larger
+    /// blocks make them grow on the audio thread once.
+    const BLOCK_CAPACITY: usize = 4096;
+
+    /// Parameter changes queued per block, see `queue_changes`.
+    const CHANGE_CAPACITY: usize = 256;
+
+    /// The oversampling to use, the offline one when rendering offline and
+    /// it's higher.
+    fn oversampling(&self, offline: bool) -> Oversampling {
+        let realtime = self.params.oversampling;
+        let offline_oversampling = self.params.offline_oversampling;
+
+        match offline {
+            true if offline_oversampling.factor() > realtime.factor() => offline_oversampling,
+            _ => realtime,
+        }
+    }
 
-    fn hann_window(i: usize, n: usize) -> f32 {
-        0.5 * (1.0 - f32::cos(2.0 * PI * i as f32 / (n - 1) as f32))
+    /// The ids of the parameters the latency depends on.
+    fn latency_params(&self, offline: bool) -> [u32; 3] {
+        [
+            self.params.phase_mode.id(),
+            self.params.resolution.id(),
+            self.oversampling(offline).id(),
+        ]
     }
 
-    pub fn compute_fft(&mut self, is_b: bool) {
-        let buffer = if is_b {
-            &mut self.buffer_b
-        } else {
-            &mut self.buffer_a
+    /// The sample rate the band chain runs at.
+    fn filter_rate(&self) -> f32 {
+        self.sample_rate * self.oversampler.factor() as f32
+    }
+
+    fn update_params(&mut self, sample_rate: f32) {
+        self.gain.set_params(
+            *self.params.input_gain,
+            *self.params.output_gain,
+            *self.params.invert,
+            *self.params.auto_gain,
+            *self.params.smoothing,
+            sample_rate,
+        );
+
+        self.bands
+            .set_solo(self.solo, &self.params.filters, sample_rate);
+        self.gain.hold = self.solo.is_some();
+
+        // the band chain runs at the oversampled rate
+        let filter_rate = sample_rate * self.oversampler.factor() as f32;
+
+        self.bands.set_params(
+            &self.params.filters,
+            *self.params.smoothing,
+            self.params.design,
+            self.params.precision,
+            filter_rate,
+        );
+
+        if let Some(ref mut linear_phase) = self.linear_phase {
+            linear_phase.update(&self.params.filters, self.params.design);
+        }
+    }
+
+    /// Run the frames in `range` through the linear phase convolver, or the
+    /// band chain at the oversampled rate.
+    fn process_block(&mut self, range: Range<usize>, channels: usize) {
+        let frames = &mut self.frames[range.clone()];
+
+        let keys = match self.keys.is_empty() {
+            true => None,
+            false => Some(&self.keys[range]),
         };
 
-        // apply window function
-        for (i, sample) in buffer.iter_mut().enumerate() {
-            *sample *= Self::hann_window(i, Self::FFT_SIZE);
+        if let Some(ref mut linear_phase) = self.linear_phase {
+            for frame in frames.iter_mut() {
+                linear_phase.process(frame);
+            }
+
+            return;
         }
 
-        self.fft
-            .process_with_scratch(buffer, &mut self.complex, &mut self.scratch)
-            .unwrap();
+        let factor = self.oversampler.factor();
+
+        if factor == 1 {
+            self.bands.process(frames, keys, channels);
+            return;
+        }
 
-        let norm = f32::powf(2.0, 2.0 / Self::FFT_SIZE as f32);
+        self.oversampler.upsample(frames, &mut self.oversampled);
 
-        for (spectrum, complex) in self.spectrum.iter_mut().zip(self.complex.iter()) {
-            let magnitude = complex.norm() * norm;
-            let magnitude = magnitude.max(1.0e-6);
+        // the sidechain only drives the detectors, so holding it is enough
+        let keys = match keys {
+            Some(keys) => {
+                let held = keys
+                    .iter()
+                    .flat_map(|key| std::iter::repeat(*key).take(factor));
 
-            *spectrum *= 0.6;
-            *spectrum += magnitude * 0.4;
+                self.oversampled_keys.clear();
+                self.oversampled_keys.extend(held);
+
+                Some(&self.oversampled_keys[..])
+            }
+            None => None,
+        };
+
+        self.bands.process(&mut self.oversampled, keys, channels);
+        self.oversampler.downsample(&self.oversampled, frames);
+    }
+
+    /// Collect the parameter changes of `buffer`, ordered by their sample
+    /// offset, so they can be applied exactly where they happen.
+    ///
+    /// Offsets past the gathered frames are moved onto the last one, changes
+    /// that don't fit the preallocated queue are applied right away.
+    fn queue_changes(&mut self, buffer: &Buffer<'_>) {
+        let last = self.frames.len().saturating_sub(1);
+
+        self.changes.clear();
+        self.next_change = 0;
+
+        for (index, mut change) in buffer.param_changes().enumerate() {
+            change.offset = usize::min(change.offset, last);
+
+            if self.changes.len() < self.changes.capacity() {
+                self.changes.push((index, change));
+            } else {
+                self.params.set_normalized(change.id, change.value);
+            }
         }
+
+        // the arrival index keeps several changes at one offset in order
+        self.changes
+            .sort_unstable_by_key(|(index, change)| (change.offset, *index));
     }
 
-    fn spectrum_x(&self, i: usize, rect: Rect) -> f32 {
-        let freq = i as f32 * self.sample_rate / Self::FFT_SIZE as f32 + 1.0;
-        freq_to_x(freq, rect)
+    /// Apply all pending parameter changes with an offset up to `offset`,
+    /// returns `true` if any parameter was changed.
+    fn apply_changes(&mut self, offset: usize) -> bool {
+        let mut applied = false;
+
+        while let Some((_, change)) = self.changes.get(self.next_change) {
+            if change.offset > offset {
+                break;
+            }
+
+            self.params.set_normalized(change.id, change.value);
+            self.next_change += 1;
+            applied = true;
+        }
+
+        applied
     }
 
-    fn spectrum_y(&self, i: usize, rect: Rect) -> f32 {
-        let freq = i as f32 * self.sample_rate / Self::FFT_SIZE as f32 + 1.0e-3;
+    fn spectrum_x(&self, freq: f32, rect: Rect) -> f32 {
+        freq_to_x(freq + 1.0, rect)
+    }
 
-        let pink_noise = 3.0 * (f32::log10(freq / 20.0) / f32::log10(2.0));
+    fn spectrum_y(&self, freq: f32, magnitude: f32, rect: Rect) -> f32 {
+        let freq = freq + 1.0e-3;
 
-        let spectrum = self.spectrum[i];
+        // tilted around 1 kHz, so a sine there reads its level
+        let tilt = self.params.analyzer_tilt.db_per_octave() * f32::log2(freq / 1000.0);
 
-        let gain = 20.0 * f32::log10(spectrum + 1.0e-6);
-        let gain = gain + pink_noise;
-        let gain = gain / 80.0;
+        let gain = 20.0 * f32::log10(magnitude + 1.0e-6);
 
-        rect.bottom() - gain * rect.height()
+        self.level_to_y(gain + tilt, rect)
+    }
+
+    /// The height of `level` in dB on the analyzer scale, which spans the
+    /// analyzer range up from the floor.
+    fn level_to_y(&self, level: f32, rect: Rect) -> f32 {
+        let level = (level - *self.params.analyzer_floor) / *self.params.analyzer_range;
+
+        rect.bottom() - level * rect.height()
+    }
+
+    /// The closed outline of `spectrum` with points at `freqs`, along the
+    /// bottom of `rect`.
+    fn spectrum_curve(&self, freqs: &[f32], spectrum: &[f32], rect: Rect) -> Curve {
+        let mut points: Vec<Point> = Vec::with_capacity(spectrum.len());
+
+        for (&freq, &magnitude) in freqs.iter().zip(spectrum) {
+            let x = self.spectrum_x(freq, rect);
+            let y = self.spectrum_y(freq, magnitude, rect);
+
+            let point = Point::new(x, y);
+
+            if let Some(last) = points.last_mut() {
+                if last.x.floor() == point.x.floor() {
+                    last.y = f32::max(last.y, point.y);
+                    continue;
+                }
+            }
+
+            points.push(point);
+        }
+
+        let mut curve = Curve::default();
+
+        curve.move_to(rect.bottom_left());
+
+        for i in 1..points.len() {
+            let a = match i == 1 {
+                true => points[0],
+                false => {
+                    let p0 = points[i - 2];
+                    let p1 = points[i - 1];
+                    let p2 = points[i];
+
+                    p1 + (p2 - p0) * SPLINE_TENSION
+                }
+            };
+
+            let b = match i == points.len() - 1 {
+                true => points[i],
+                false => {
+                    let p0 = points[i - 1];
+                    let p1 = points[i];
+                    let p2 = points[i + 1];
+
+                    p1 - (p2 - p0) * SPLINE_TENSION
+                }
+            };
+
+            let c = points[i];
+
+            curve.cubic_to(a, b, c);
+        }
+
+        curve.line_to(rect.bottom_right());
+        curve.close();
+
+        curve
     }
 }
 
@@ -233,9 +651,28 @@ const FREQ_TEXT: &[f32] = &[
     20.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0,
 ];
 
+/// Spacings in dB of the analyzer scale, the finest that fits the range in
+/// `ANALYZER_TICKS` ticks is used.
+const ANALYZER_STEPS: &[f32] = &[10.0, 20.0, 30.0, 40.0];
+const ANALYZER_TICKS: f32 = 6.0;
+
+/// Widths in octaves the match smoothing steps through.
+const SMOOTHING_STEPS: &[f32] = &[1.0 / 12.0, 1.0 / 6.0, 1.0 / 3.0, 1.0 / 2.0, 1.0, 2.0];
+
+/// Analyzer overlaps in % the ui steps through.
+const OVERLAP_STEPS: &[f32] = &[0.0, 50.0, 75.0, 87.5, 93.75];
+
+/// Analyzer peak hold times in ms the ui steps through.
+const HOLD_STEPS: &[f32] = &[0.0, 250.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0];
+
+/// Analyzer decay times in ms the ui steps through.
+const DECAY_STEPS: &[f32] = &[0.0, 25.0, 50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0];
+
 #[derive(Default)]
 struct CurveView {
     selected: Option<usize>,
+    /// Whether the selected band is soloed for as long as it's dragged.
+    soloing: bool,
     last_click: Option<Instant>,
 }
 
@@ -320,62 +757,97 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                 cx.text(&buffer, label_color, text_offset);
             }
 
-            cx.masked(rect, |cx| {
-                let mut points: Vec<Point> = Vec::with_capacity(data.spectrum.len());
+            // the analyzer has its own scale, set by the floor and the
+            // range, ticked along the left edge
+            let floor = *data.params.analyzer_floor;
+            let range = *data.params.analyzer_range;
 
-                for i in 0..data.spectrum.len() {
-                    let x = data.spectrum_x(i, rect);
-                    let y = data.spectrum_y(i, rect);
+            let step = ANALYZER_STEPS
+                .iter()
+                .copied()
+                .find(|step| range / step <= ANALYZER_TICKS)
+                .unwrap_or(ANALYZER_STEPS[ANALYZER_STEPS.len() - 1]);
 
-                    let point = Point::new(x, y);
+            let mut level = f32::ceil(floor / step) * step;
 
-                    if let Some(last) = points.last_mut() {
-                        if last.x.floor() == point.x.floor() {
-                            last.y = f32::max(last.y, point.y);
-                            continue;
-                        }
-                    }
+            while level <= floor + range {
+                let y = data.level_to_y(level, rect);
 
-                    points.push(point);
-                }
+                // full scale gets a line across, where a 0 dBFS sine peaks
+                let (end, label) = match level == 0.0 {
+                    true => (rect.max.x, String::from("0 dBFS")),
+                    false => (rect.min.x + 6.0, format!("{:.0}", level)),
+                };
 
                 let mut curve = Curve::default();
+                curve.move_to(Point::new(rect.min.x, y));
+                curve.line_to(Point::new(end, y));
 
-                curve.move_to(rect.bottom_left());
+                cx.stroke(curve, 1.0, label_color);
 
-                for i in 1..points.len() {
-                    let a = match i == 1 {
-                        true => points[0],
-                        false => {
-                            let p0 = points[i - 2];
-                            let p1 = points[i - 1];
-                            let p2 = points[i];
+                let mut text = TextBuffer::new(cx.fonts(), 12.0, 1.0);
+                text.set_text(cx.fonts(), &label, Default::default());
 
-                            p1 + (p2 - p0) * SPLINE_TENSION
-                        }
-                    };
+                // kept inside the view at the top and bottom
+                let text_y = f32::clamp(y - 6.0, rect.min.y, rect.max.y - 14.0);
 
-                    let b = match i == points.len() - 1 {
-                        true => points[i],
-                        false => {
-                            let p0 = points[i - 1];
-                            let p1 = points[i];
-                            let p2 = points[i + 1];
+                cx.text(&text, label_color, Vector::new(rect.min.x + 8.0, text_y));
 
-                            p1 - (p2 - p0) * SPLINE_TENSION
-                        }
-                    };
+                level += step;
+            }
+
+            cx.masked(rect, |cx| {
+                let traces = data.params.analyzer_traces;
+
+                data.spectra.update();
+                let spectra = data.spectra.get();
+
+                // the input is only outlined, so the output stays readable
+                // on top of it
+                if traces.shows_pre() {
+                    for (i, spectrum) in spectra.pre.iter().enumerate() {
+                        let color = match spectra.pre.len() {
+                            1 => label_color,
+                            _ => trace_color(i),
+                        };
+
+                        let curve = data.spectrum_curve(&spectra.freqs, spectrum, rect);
+                        cx.stroke(curve, 1.0, color.fade(0.8));
+                    }
+                }
 
-                    let c = points[i];
+                if traces.shows_post() {
+                    for (i, spectrum) in spectra.post.iter().enumerate() {
+                        let color = match spectra.post.len() {
+                            1 => contrast_color,
+                            _ => trace_color(i),
+                        };
 
-                    curve.cubic_to(a, b, c);
+                        let curve = data.spectrum_curve(&spectra.freqs, spectrum, rect);
+
+                        cx.fill(curve.clone(), FillRule::NonZero, color.fade(0.1));
+                        cx.stroke(curve, 1.0, color.fade(0.5));
+                    }
                 }
 
-                curve.line_to(rect.bottom_right());
-                curve.close();
+                if *data.params.analyzer_peak {
+                    for (i, spectrum) in spectra.peak.iter().enumerate() {
+                        let color = match spectra.peak.len() {
+                            1 => contrast_color,
+                            _ => trace_color(i),
+                        };
 
-                cx.fill(curve.clone(), FillRule::NonZero, contrast_color.fade(0.1));
-                cx.stroke(curve, 1.0, contrast_color.fade(0.5));
+                        let curve = data.spectrum_curve(&spectra.freqs, spectrum, rect);
+                        cx.stroke(curve, 1.0, color.fade(0.8));
+                    }
+                }
+
+                if *data.params.analyzer_max {
+                    for spectrum in spectra.max.iter() {
+                        let curve = data.spectrum_curve(&spectra.freqs, spectrum, rect);
+                        cx.stroke(curve, 1.0, max_color().fade(0.8));
+                    }
+                }
 
                 for (i, filter) in data.params.filters.iter().enumerate() {
                     let mut curve = Curve::default();
@@ -386,7 +858,7 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                         let frac = i as f32 / 255.0;
                         let freq = frac_to_freq(frac);
 
-                        let gain = filter.gain_at(freq, 24000.0 + frac * 24000.0);
+                        let gain = filter.gain_at(freq, data.filter_rate(), data.params.design);
 
                         let x = freq_to_x(freq, rect);
                         let y = gain_to_y(gain, rect);
@@ -405,6 +877,31 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                         true => cx.fill(curve, FillRule::NonZero, color.fade(0.4)),
                         false => cx.fill(curve, FillRule::NonZero, color.fade(0.3).desaturate(0.3)),
                     }
+
+                    // draw the live response of dynamic bands on top of the
+                    // static setting
+                    if data.bands.dynamics[i].enabled {
+                        let state = &data.bands.filters[i];
+
+                        let mut curve = Curve::default();
+
+                        for j in 0..256 {
+                            let frac = j as f32 / 255.0;
+                            let freq = frac_to_freq(frac);
+
+                            let gain = state.gain_at(freq, data.filter_rate());
+
+                            let point = Point::new(freq_to_x(freq, rect), gain_to_y(gain, rect));
+
+                            if j == 0 {
+                                curve.move_to(point);
+                            } else {
+                                curve.line_to(point);
+                            }
+                        }
+
+                        cx.stroke(curve, 1.5, color);
+                    }
                 }
 
                 let mut curve = Curve::default();
@@ -422,7 +919,7 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                             continue;
                         }
 
-                        gain += filter.gain_at(freq, 24000.0 + frac * 24000.0);
+                        gain += filter.gain_at(freq, data.filter_rate(), data.params.design);
                     }
 
                     let y = gain_to_y(gain, rect);
@@ -457,6 +954,21 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                         FillRule::NonZero,
                         color.darken(0.3).desaturate(0.2),
                     );
+
+                    // mark bands that only process part of the stereo signal
+                    if !matches!(filter.placement, Placement::Stereo) {
+                        let mut text = TextBuffer::new(cx.fonts(), 10.0, 1.0);
+
+                        text.set_text(
+                            cx.fonts(),
+                            filter.placement.abbreviation(),
+                            Default::default(),
+                        );
+
+                        let text_offset = Vector::new(center.x - 3.0, center.y - 6.0);
+
+                        cx.text(&text, color, text_offset);
+                    }
                 }
             });
 
@@ -483,6 +995,12 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                     }
 
                     let Some(selected) = selected else {
+                        // clicking the background starts the max over
+                        if e.button == PointerButton::Primary && *data.params.analyzer_max {
+                            data.analyzer.clear_max();
+                            return true;
+                        }
+
                         return false;
                     };
 
@@ -490,6 +1008,14 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                         PointerButton::Primary => {
                             state.selected = Some(selected);
 
+                            // audition the band while dragging it with alt held
+                            if e.modifiers.alt {
+                                state.soloing = true;
+                                data.solo = Some(selected);
+
+                                cx.rebuild();
+                            }
+
                             if state.is_double_click() {
                                 data.params.filters[selected] = Filter::new(selected as u32, 10);
 
@@ -527,7 +1053,7 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                         *filter.freq = filter.freq.clamp(Filter::FREQ_MIN, Filter::FREQ_MAX);
                         *filter.gain = filter.gain.clamp(Filter::GAIN_MIN, Filter::GAIN_MAX);
 
-                        if !filter.kind.uses_gain() {
+                        if !filter.kind().uses_gain() {
                             *filter.gain = 0.0;
                         }
 
@@ -538,6 +1064,13 @@ fn curve_view(_data: &mut Freeq) -> impl View<Freeq> {
                     false
                 }
                 Event::PointerReleased(e) if e.button == PointerButton::Primary => {
+                    if state.soloing {
+                        state.soloing = false;
+                        data.solo = None;
+
+                        cx.rebuild();
+                    }
+
                     state.selected.take().is_some()
                 }
                 Event::PointerScrolled(e) => {
@@ -589,9 +1122,9 @@ fn filter_options(data: &mut Freeq, index: usize) -> impl View<Freeq> {
     let prev_kind = text("<").font_size(14.0);
     let prev_kind = button(prev_kind).padding(2.0).color(Theme::SURFACE);
     let prev_kind = on_click(prev_kind, move |cx, filter: &mut Filter| {
-        filter.kind = filter.kind.prev();
+        filter.set_kind(filter.kind().prev());
 
-        if !filter.kind.uses_gain() {
+        if !filter.kind().uses_gain() {
             *filter.gain = 0.0;
         }
 
@@ -602,9 +1135,9 @@ fn filter_options(data: &mut Freeq, index: usize) -> impl View<Freeq> {
     let next_kind = text(">").font_size(14.0);
     let next_kind = button(next_kind).padding(2.0).color(Theme::SURFACE);
     let next_kind = on_click(next_kind, move |cx, filter: &mut Filter| {
-        filter.kind = filter.kind.next();
+        filter.set_kind(filter.kind().next());
 
-        if !filter.kind.uses_gain() {
+        if !filter.kind().uses_gain() {
             *filter.gain = 0.0;
         }
 
@@ -612,10 +1145,38 @@ fn filter_options(data: &mut Freeq, index: usize) -> impl View<Freeq> {
         cx.draw();
     });
 
-    let kind = text(filter.kind.abbreviation()).font_size(14.0);
+    let kind = text(filter.kind().abbreviation()).font_size(14.0);
     let kind = hstack![prev_kind, kind, next_kind].justify(Justify::SpaceBetween);
     let kind = width(FILL, pad([6.0, 0.0], kind));
 
+    let slope = match filter.kind().uses_slope() {
+        true => filter.slope.abbreviation(),
+        false => "-",
+    };
+
+    let slope = text(slope).font_size(14.0);
+    let slope = button(slope).padding(2.0).color(Theme::SURFACE);
+    let slope = on_click(slope, move |cx, filter: &mut Filter| {
+        filter.slope = filter.slope.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let shape = match filter.kind().uses_slope() {
+        true => filter.shape.abbreviation(),
+        false => "-",
+    };
+
+    let shape = text(shape).font_size(14.0);
+    let shape = button(shape).padding(2.0).color(Theme::SURFACE);
+    let shape = on_click(shape, move |cx, filter: &mut Filter| {
+        filter.shape = filter.shape.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
     let freq = match *filter.freq < 1000.0 {
         true => format!("{:.0} Hz", *filter.freq),
         false => format!("{:.1} kHz", *filter.freq / 1000.0),
@@ -627,7 +1188,61 @@ fn filter_options(data: &mut Freeq, index: usize) -> impl View<Freeq> {
 
     let q = text(format!("{:.2}", *filter.q)).font_size(14.0);
 
-    let view = vstack![kind, freq, gain, q].gap(2.0);
+    let dynamic = match (*filter.dynamic, filter.detection) {
+        (false, _) => "Static",
+        (true, Detection::Internal) => "Dynamic",
+        (true, Detection::External) => "Sidechain",
+    };
+
+    // cycle through static, dynamic with internal and with external detection
+    let dynamic = text(dynamic).font_size(14.0);
+    let dynamic = button(dynamic).padding(2.0).color(Theme::SURFACE);
+    let dynamic = on_click(dynamic, move |cx, filter: &mut Filter| {
+        match (*filter.dynamic, filter.detection) {
+            (false, _) => {
+                *filter.dynamic = true;
+                filter.detection = Detection::Internal;
+            }
+            (true, Detection::Internal) => filter.detection = Detection::External,
+            (true, Detection::External) => *filter.dynamic = false,
+        }
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let placement = text(filter.placement.abbreviation()).font_size(14.0);
+    let placement = button(placement).padding(2.0).color(Theme::SURFACE);
+    let placement = on_click(placement, move |cx, filter: &mut Filter| {
+        filter.placement = filter.placement.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let options = vstack![kind, slope, shape, freq, gain, q, dynamic, placement].gap(2.0);
+    let options = focus(options, move |data: &mut Freeq, lens| {
+        lens(&mut data.params.filters[index])
+    });
+
+    let soloed = data.solo == Some(index);
+
+    let solo = text("Solo").font_size(14.0);
+    let solo = match soloed {
+        true => button(solo).padding(2.0).color(color),
+        false => button(solo).padding(2.0).color(Theme::SURFACE),
+    };
+    let solo = on_click(solo, move |cx, data: &mut Freeq| {
+        data.solo = match soloed {
+            true => None,
+            false => Some(index),
+        };
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let view = vstack![options, solo].gap(2.0);
 
     let view = pad([8.0, 2.0, 2.0, 2.0], view);
     let view = container(view)
@@ -635,10 +1250,300 @@ fn filter_options(data: &mut Freeq, index: usize) -> impl View<Freeq> {
         .border_radius(2.0)
         .border_color(color);
 
-    let view = width(64.0, view);
+    width(64.0, view)
+}
 
-    focus(view, move |data: &mut Freeq, lens| {
-        lens(&mut data.params.filters[index])
+fn match_options(data: &mut Freeq) -> impl View<Freeq> {
+    let title = text("Match").font_size(14.0);
+
+    let reference = capture_button(data, Capture::Reference);
+    let source = capture_button(data, Capture::Source);
+
+    let amount = stepper(
+        format!("{:.0}%", *data.params.match_amount),
+        |data| *data.params.match_amount = (*data.params.match_amount - 10.0).max(0.0),
+        |data| *data.params.match_amount = (*data.params.match_amount + 10.0).min(100.0),
+    );
+
+    let smoothing = match *data.params.match_smoothing < 1.0 {
+        true => format!("1/{:.0}", 1.0 / *data.params.match_smoothing),
+        false => format!("{:.0}", *data.params.match_smoothing),
+    };
+
+    let smoothing = stepper(
+        smoothing,
+        |data| {
+            let smoothing = &mut data.params.match_smoothing;
+            **smoothing = step_through(SMOOTHING_STEPS, **smoothing, false);
+        },
+        |data| {
+            let smoothing = &mut data.params.match_smoothing;
+            **smoothing = step_through(SMOOTHING_STEPS, **smoothing, true);
+        },
+    );
+
+    let apply = text("Apply").font_size(14.0);
+    let apply = button(apply).padding(2.0).color(Theme::SURFACE);
+    let apply = on_click(apply, move |cx, data: &mut Freeq| {
+        let amount = *data.params.match_amount / 100.0;
+        let smoothing = *data.params.match_smoothing;
+        let filter_rate = data.filter_rate();
+
+        let matching = data.matching.lock().unwrap();
+
+        let fitted = matching.fit(
+            &mut data.params.filters,
+            amount,
+            smoothing,
+            data.params.design,
+            data.sample_rate,
+            filter_rate,
+        );
+
+        if fitted {
+            cx.rebuild();
+            cx.draw();
+        }
+    });
+
+    let view = vstack![title, reference, source, amount, smoothing, apply].gap(2.0);
+
+    let view = pad([8.0, 2.0, 2.0, 2.0], view);
+    let view = container(view)
+        .border_width([6.0, 0.0, 0.0, 0.0])
+        .border_radius(2.0)
+        .border_color(Color::okhsl(0.0, 0.0, 0.6));
+
+    width(80.0, view)
+}
+
+fn analyzer_options(data: &mut Freeq) -> impl View<Freeq> {
+    let title = text("Analyzer").font_size(14.0);
+
+    let traces = text(data.params.analyzer_traces.abbreviation()).font_size(14.0);
+    let traces = button(traces).padding(2.0).color(Theme::SURFACE);
+    let traces = on_click(traces, move |cx, data: &mut Freeq| {
+        data.params.analyzer_traces = data.params.analyzer_traces.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let size = stepper(
+        format!("{}k", data.params.analyzer_size.size() / 1024),
+        |data| data.params.analyzer_size = data.params.analyzer_size.prev(),
+        |data| data.params.analyzer_size = data.params.analyzer_size.next(),
+    );
+
+    let resolution = text(data.params.analyzer_resolution.abbreviation()).font_size(14.0);
+    let resolution = button(resolution).padding(2.0).color(Theme::SURFACE);
+    let resolution = on_click(resolution, move |cx, data: &mut Freeq| {
+        data.params.analyzer_resolution = data.params.analyzer_resolution.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let overlap = stepper(
+        format!("{:.0}%", *data.params.analyzer_overlap),
+        |data| {
+            let overlap = &mut data.params.analyzer_overlap;
+            **overlap = step_through(OVERLAP_STEPS, **overlap, false);
+        },
+        |data| {
+            let overlap = &mut data.params.analyzer_overlap;
+            **overlap = step_through(OVERLAP_STEPS, **overlap, true);
+        },
+    );
+
+    let window = text(data.params.analyzer_window.abbreviation()).font_size(14.0);
+    let window = button(window).padding(2.0).color(Theme::SURFACE);
+    let window = on_click(window, move |cx, data: &mut Freeq| {
+        data.params.analyzer_window = data.params.analyzer_window.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let decay = stepper(
+        format!("{:.0} ms", *data.params.analyzer_decay),
+        |data| {
+            let decay = &mut data.params.analyzer_decay;
+            **decay = step_through(DECAY_STEPS, **decay, false);
+        },
+        |data| {
+            let decay = &mut data.params.analyzer_decay;
+            **decay = step_through(DECAY_STEPS, **decay, true);
+        },
+    );
+
+    let range = stepper(
+        format!("{:.0} dB", *data.params.analyzer_range),
+        |data| *data.params.analyzer_range = (*data.params.analyzer_range - 10.0).max(20.0),
+        |data| *data.params.analyzer_range = (*data.params.analyzer_range + 10.0).min(160.0),
+    );
+
+    let floor = stepper(
+        format!("{:+.0} dB", *data.params.analyzer_floor),
+        |data| *data.params.analyzer_floor = (*data.params.analyzer_floor - 10.0).max(-160.0),
+        |data| *data.params.analyzer_floor = (*data.params.analyzer_floor + 10.0).min(0.0),
+    );
+
+    let smoothing = text(data.params.analyzer_smoothing.abbreviation()).font_size(14.0);
+    let smoothing = button(smoothing).padding(2.0).color(Theme::SURFACE);
+    let smoothing = on_click(smoothing, move |cx, data: &mut Freeq| {
+        data.params.analyzer_smoothing = data.params.analyzer_smoothing.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let tilt = text(data.params.analyzer_tilt.abbreviation()).font_size(14.0);
+    let tilt = button(tilt).padding(2.0).color(Theme::SURFACE);
+    let tilt = on_click(tilt, move |cx, data: &mut Freeq| {
+        data.params.analyzer_tilt = data.params.analyzer_tilt.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let peak_on = *data.params.analyzer_peak;
+    let peak = text("Peak").font_size(14.0);
+    let peak = match peak_on {
+        true => button(peak).padding(2.0).color(trace_color(0)),
+        false => button(peak).padding(2.0).color(Theme::SURFACE),
+    };
+    let peak = on_click(peak, move |cx, data: &mut Freeq| {
+        *data.params.analyzer_peak = !peak_on;
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let hold = stepper(
+        format!("{:.1} s", *data.params.analyzer_hold / 1000.0),
+        |data| {
+            let hold = &mut data.params.analyzer_hold;
+            **hold = step_through(HOLD_STEPS, **hold, false);
+        },
+        |data| {
+            let hold = &mut data.params.analyzer_hold;
+            **hold = step_through(HOLD_STEPS, **hold, true);
+        },
+    );
+
+    let fall = stepper(
+        format!("{:.0} dB/s", *data.params.analyzer_fall),
+        |data| *data.params.analyzer_fall = (*data.params.analyzer_fall / 2.0).max(3.0),
+        |data| *data.params.analyzer_fall = (*data.params.analyzer_fall * 2.0).min(96.0),
+    );
+
+    let max_on = *data.params.analyzer_max;
+    let max = text("Max").font_size(14.0);
+    let max = match max_on {
+        true => button(max).padding(2.0).color(max_color()),
+        false => button(max).padding(2.0).color(Theme::SURFACE),
+    };
+    let max = on_click(max, move |cx, data: &mut Freeq| {
+        *data.params.analyzer_max = !max_on;
+
+        // a max shown again starts from now
+        if !max_on {
+            data.analyzer.clear_max();
+        }
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let stereo = text(data.params.analyzer_stereo.abbreviation()).font_size(14.0);
+    let stereo = button(stereo).padding(2.0).color(Theme::SURFACE);
+    let stereo = on_click(stereo, move |cx, data: &mut Freeq| {
+        data.params.analyzer_stereo = data.params.analyzer_stereo.next();
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let analysis = vstack![traces, stereo, resolution, size, overlap, window, decay].gap(2.0);
+    let display = vstack![smoothing, tilt, range, floor, peak, hold, fall, max].gap(2.0);
+
+    // two columns, so the panel doesn't get taller than the bands
+    let columns = hstack![width(FILL, analysis), width(FILL, display)].gap(4.0);
+    let view = vstack![title, columns].gap(2.0);
+
+    let view = pad([8.0, 2.0, 2.0, 2.0], view);
+    let view = container(view)
+        .border_width([6.0, 0.0, 0.0, 0.0])
+        .border_radius(2.0)
+        .border_color(Color::okhsl(0.0, 0.0, 0.6));
+
+    width(164.0, view)
+}
+
+/// A value with buttons stepping it down and up.
+fn stepper(
+    value: String,
+    down: impl Fn(&mut Freeq) + 'static,
+    up: impl Fn(&mut Freeq) + 'static,
+) -> impl View<Freeq> {
+    let down_button = text("<").font_size(14.0);
+    let down_button = button(down_button).padding(2.0).color(Theme::SURFACE);
+    let down_button = on_click(down_button, move |cx, data: &mut Freeq| {
+        down(data);
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let up_button = text(">").font_size(14.0);
+    let up_button = button(up_button).padding(2.0).color(Theme::SURFACE);
+    let up_button = on_click(up_button, move |cx, data: &mut Freeq| {
+        up(data);
+
+        cx.rebuild();
+        cx.draw();
+    });
+
+    let value = text(value).font_size(14.0);
+    let view = hstack![down_button, value, up_button].justify(Justify::SpaceBetween);
+
+    width(FILL, pad([6.0, 0.0], view))
+}
+
+/// The next of `steps` above or below `value`, or `value` if there is none.
+fn step_through(steps: &[f32], value: f32, up: bool) -> f32 {
+    let step = match up {
+        true => steps.iter().find(|&&step| step > value * 1.01),
+        false => steps.iter().rev().find(|&&step| step < value * 0.99),
+    };
+
+    step.copied().unwrap_or(value)
+}
+
+/// A button starting and stopping the capture of `capture`.
+fn capture_button(data: &mut Freeq, capture: Capture) -> impl View<Freeq> {
+    let matching = data.matching.lock().unwrap();
+
+    let (label, captured) = match capture {
+        Capture::Reference => ("Ref", matching.has_reference()),
+        Capture::Source => ("Source", matching.has_source()),
+    };
+
+    let label = match (matching.capturing == Some(capture), captured) {
+        (true, _) => String::from("Stop"),
+        (false, true) => format!("{} *", label),
+        (false, false) => String::from(label),
+    };
+
+    let view = text(label).font_size(14.0);
+    let view = button(view).padding(2.0).color(Theme::SURFACE);
+
+    on_click(view, move |cx, data: &mut Freeq| {
+        data.matching.lock().unwrap().toggle(capture);
+
+        cx.rebuild();
+        cx.draw();
     })
 }
 
@@ -649,6 +1554,19 @@ fn curve_view_rect(rect: Rect) -> Rect {
     )
 }
 
+/// Color of the left or mid trace at 0 and the right or side trace at 1.
+fn trace_color(index: usize) -> Color {
+    match index {
+        0 => Color::okhsl(210.0, 0.8, 0.7),
+        _ => Color::okhsl(30.0, 0.8, 0.7),
+    }
+}
+
+/// Color of the max since the analyzer was cleared.
+fn max_color() -> Color {
+    Color::okhsl(0.0, 0.8, 0.7)
+}
+
 fn filter_color(index: usize, max: usize) -> Color {
     let hue = index as f32 / max as f32;
 
@@ -0,0 +1,806 @@
+use std::{
+    f32::consts::PI,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use num::Complex;
+use ori_vst::prelude::*;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+
+use crate::{
+    filter::{Filter, FilterDesign, FilterKind, FilterState, Placement, Shape, Slope},
+    lock_free::{triple_buffer, Reader, Writer},
+};
+
+/// Linear phase convolution of the summed band response.
+///
+/// The bands are turned into a 2x2 matrix of zero phase kernels, so that
+/// left, right, mid and side bands all stay exact, and applied with
+/// uniformly partitioned overlap-save convolution. The kernels are built on
+/// a designer thread and handed back through a triple buffer, so the audio
+/// thread never runs the kernel FFTs.
+pub struct LinearPhase {
+    block: usize,
+    partitions: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    /// Partitioned kernel spectra indexed by `[row * 2 + column][partition][bin]`.
+    kernels: Vec<Complex<f32>>,
+    next_kernels: Vec<Complex<f32>>,
+    crossfade: bool,
+    /// Frequency domain delay line of the input spectra, `[channel][partition][bin]`.
+    history: Vec<Complex<f32>>,
+    head: usize,
+    input: [Vec<f32>; 2],
+    output: [Vec<f32>; 2],
+    position: usize,
+    time: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    accumulator: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    fingerprint: Vec<f32>,
+    requests: Writer<Request>,
+    built: Reader<Vec<Complex<f32>>>,
+    _designer: DesignerThread,
+}
+
+impl LinearPhase {
+    /// Create the convolver for `bands` bands and start its designer thread.
+    pub fn new(resolution: Resolution, bands: usize, sample_rate: f32) -> Self {
+        let block = resolution.block();
+        let partitions = resolution.partitions();
+        let bins = block + 1;
+
+        let mut planner = RealFftPlanner::new();
+        let fft = planner.plan_fft_forward(block * 2);
+        let ifft = planner.plan_fft_inverse(block * 2);
+
+        let scratch_len = usize::max(fft.get_scratch_len(), ifft.get_scratch_len());
+
+        let mut builder = KernelBuilder::new(resolution, sample_rate);
+
+        // start out with a kernel that passes the signal unchanged
+        let mut kernels = vec![Complex::new(0.0, 0.0); 4 * partitions * bins];
+        builder.build(&[], FilterDesign::Rbj, &mut kernels);
+
+        let request = Request {
+            bands: vec![Band::default(); bands],
+            design: FilterDesign::Rbj,
+        };
+
+        let (requests, designer_requests) = triple_buffer(request);
+        let (designer_kernels, built) = triple_buffer(kernels.clone());
+
+        let designer = DesignerThread::spawn(Designer {
+            filters: (0..bands)
+                .map(|i| Filter::new(i as u32, bands as u32))
+                .collect(),
+            builder,
+            requests: designer_requests,
+            kernels: designer_kernels,
+        });
+
+        Self {
+            block,
+            partitions,
+            next_kernels: kernels.clone(),
+            kernels,
+            crossfade: false,
+            history: vec![Complex::new(0.0, 0.0); 2 * partitions * bins],
+            head: 0,
+            input: [vec![0.0; block * 2], vec![0.0; block * 2]],
+            output: [vec![0.0; block], vec![0.0; block]],
+            position: 0,
+            time: vec![0.0; block * 2],
+            spectrum: vec![Complex::new(0.0, 0.0); bins],
+            accumulator: vec![Complex::new(0.0, 0.0); bins],
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            fingerprint: Vec::with_capacity(128),
+            requests,
+            built,
+            _designer: designer,
+            fft,
+            ifft,
+        }
+    }
+
+    /// The delay in samples, the block buffering plus the center of the kernel.
+    pub fn latency(&self) -> usize {
+        self.block + self.block * self.partitions / 2
+    }
+
+    /// Hand the bands to the designer thread if any changed since the last
+    /// call, the kernels it builds are faded in once they arrive.
+    pub fn update(&mut self, filters: &[Filter], design: FilterDesign) {
+        let fingerprint = filters.iter().flat_map(|filter| {
+            [
+                *filter.enabled as u32 as f32,
+                *filter.freq,
+                *filter.gain,
+                *filter.q,
+                filter.kind().id() as f32,
+                filter.slope.id() as f32,
+                filter.shape.id() as f32,
+                filter.placement.id() as f32,
+            ]
+        });
+        let fingerprint = fingerprint.chain([design.id() as f32]);
+
+        if self.fingerprint.iter().copied().eq(fingerprint.clone()) {
+            return;
+        }
+
+        self.fingerprint.clear();
+        self.fingerprint.extend(fingerprint);
+
+        let request = self.requests.slot();
+
+        for (band, filter) in request.bands.iter_mut().zip(filters) {
+            *band = Band::new(filter);
+        }
+
+        request.design = design;
+        self.requests.publish();
+    }
+
+    /// Process one sample per channel, the output is delayed by `latency`.
+    pub fn process(&mut self, frame: &mut [f32; 2]) {
+        for (channel, sample) in frame.iter_mut().enumerate() {
+            self.input[channel][self.block + self.position] = *sample;
+            *sample = self.output[channel][self.position];
+        }
+
+        self.position += 1;
+
+        if self.position == self.block {
+            self.position = 0;
+            self.process_block();
+        }
+    }
+
+    fn process_block(&mut self) {
+        let bins = self.block + 1;
+        let scale = 1.0 / (self.block * 2) as f32;
+
+        // fade in the newest kernels from the designer over this block
+        if self.built.update() {
+            self.next_kernels.copy_from_slice(self.built.get());
+            self.crossfade = true;
+        }
+
+        self.head = (self.head + self.partitions - 1) % self.partitions;
+
+        for channel in 0..2 {
+            self.time.copy_from_slice(&self.input[channel]);
+
+            let offset = (channel * self.partitions + self.head) * bins;
+            let history = &mut self.history[offset..offset + bins];
+
+            self.fft
+                .process_with_scratch(&mut self.time, history, &mut self.scratch)
+                .unwrap();
+
+            // keep the newest block as the overlap of the next one
+            self.input[channel].copy_within(self.block.., 0);
+        }
+
+        for row in 0..2 {
+            self.convolve(row, false);
+
+            for (output, sample) in self.output[row].iter_mut().zip(&self.time[self.block..]) {
+                *output = *sample * scale;
+            }
+
+            if !self.crossfade {
+                continue;
+            }
+
+            self.convolve(row, true);
+
+            for (i, (output, sample)) in self.output[row]
+                .iter_mut()
+                .zip(&self.time[self.block..])
+                .enumerate()
+            {
+                let fade = i as f32 / self.block as f32;
+                *output = *output * (1.0 - fade) + *sample * scale * fade;
+            }
+        }
+
+        if self.crossfade {
+            std::mem::swap(&mut self.kernels, &mut self.next_kernels);
+            self.crossfade = false;
+        }
+    }
+
+    /// Convolve both input channels with the kernels of `row` into `self.time`.
+    fn convolve(&mut self, row: usize, next: bool) {
+        let bins = self.block + 1;
+
+        let kernels = match next {
+            true => &self.next_kernels,
+            false => &self.kernels,
+        };
+
+        self.accumulator.fill(Complex::new(0.0, 0.0));
+
+        for column in 0..2 {
+            for partition in 0..self.partitions {
+                let slot = (self.head + partition) % self.partitions;
+
+                let input = (column * self.partitions + slot) * bins;
+                let kernel = ((row * 2 + column) * self.partitions + partition) * bins;
+
+                let input = &self.history[input..input + bins];
+                let kernel = &kernels[kernel..kernel + bins];
+
+                for ((accumulator, input), kernel) in
+                    self.accumulator.iter_mut().zip(input).zip(kernel)
+                {
+                    *accumulator += input * kernel;
+                }
+            }
+        }
+
+        // the imaginary parts at dc and nyquist must be exactly zero
+        self.spectrum.copy_from_slice(&self.accumulator);
+        self.spectrum[0].im = 0.0;
+        self.spectrum[bins - 1].im = 0.0;
+
+        self.ifft
+            .process_with_scratch(&mut self.spectrum, &mut self.time, &mut self.scratch)
+            .unwrap();
+    }
+}
+
+/// The parameters of a band the kernels depend on, copied out of the `Filter`
+/// so they can be handed to the designer thread without allocating.
+#[derive(Clone, Copy, Debug, Default)]
+struct Band {
+    enabled: bool,
+    freq: f32,
+    gain: f32,
+    q: f32,
+    kind: FilterKind,
+    slope: Slope,
+    shape: Shape,
+    placement: Placement,
+}
+
+impl Band {
+    fn new(filter: &Filter) -> Self {
+        Self {
+            enabled: *filter.enabled,
+            freq: *filter.freq,
+            gain: *filter.gain,
+            q: *filter.q,
+            kind: filter.kind(),
+            slope: filter.slope,
+            shape: filter.shape,
+            placement: filter.placement,
+        }
+    }
+
+    fn apply(&self, filter: &mut Filter) {
+        *filter.enabled = self.enabled;
+        *filter.freq = self.freq;
+        *filter.gain = self.gain;
+        *filter.q = self.q;
+        filter.set_kind(self.kind);
+        filter.slope = self.slope;
+        filter.shape = self.shape;
+        filter.placement = self.placement;
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Request {
+    bands: Vec<Band>,
+    design: FilterDesign,
+}
+
+/// Stops and joins the designer thread when dropped.
+struct DesignerThread {
+    running: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DesignerThread {
+    fn spawn(mut designer: Designer) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = running.clone();
+
+        let handle = thread::Builder::new()
+            .name(String::from("freeq linear phase"))
+            .spawn(move || designer.run(&flag))
+            .expect("failed to spawn the linear phase thread");
+
+        Self {
+            running,
+            handle: Some(handle),
+        }
+    }
+}
+
+impl Drop for DesignerThread {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Builds the kernels for the latest request of the audio thread.
+struct Designer {
+    /// Scratch filters the requested bands are written into.
+    filters: Vec<Filter>,
+    builder: KernelBuilder,
+    requests: Reader<Request>,
+    kernels: Writer<Vec<Complex<f32>>>,
+}
+
+impl Designer {
+    /// How often the designer checks for new requests.
+    const INTERVAL: Duration = Duration::from_millis(5);
+
+    fn run(&mut self, running: &AtomicBool) {
+        while running.load(Ordering::Relaxed) {
+            // requests made while building are picked up on the next round,
+            // so only the newest one is ever built
+            if self.requests.update() {
+                let request = self.requests.get();
+
+                for (filter, band) in self.filters.iter_mut().zip(&request.bands) {
+                    band.apply(filter);
+                }
+
+                self.builder
+                    .build(&self.filters, request.design, self.kernels.slot());
+                self.kernels.publish();
+            }
+
+            thread::sleep(Self::INTERVAL);
+        }
+    }
+}
+
+/// Turns the band magnitudes into partitioned kernel spectra.
+struct KernelBuilder {
+    block: usize,
+    partitions: usize,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    matrix: Vec<[f32; 4]>,
+    half_angles: Vec<(f32, f32)>,
+    kernel: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    time: Vec<f32>,
+    scratch: Vec<Complex<f32>>,
+    sample_rate: f32,
+}
+
+impl KernelBuilder {
+    fn new(resolution: Resolution, sample_rate: f32) -> Self {
+        let block = resolution.block();
+        let partitions = resolution.partitions();
+        let length = block * partitions;
+
+        let mut planner = RealFftPlanner::new();
+        let fft = planner.plan_fft_forward(block * 2);
+        let ifft = planner.plan_fft_inverse(length);
+
+        let scratch_len = usize::max(fft.get_scratch_len(), ifft.get_scratch_len());
+
+        // frequencies of the kernel bins, as the half angles needed by
+        // `FilterState::magnitude`
+        let half_angles = (0..length / 2 + 1)
+            .map(|i| f32::sin_cos(PI * i as f32 / length as f32))
+            .collect();
+
+        Self {
+            block,
+            partitions,
+            fft,
+            ifft,
+            matrix: vec![[1.0, 0.0, 0.0, 1.0]; length / 2 + 1],
+            half_angles,
+            kernel: vec![0.0; length],
+            spectrum: vec![Complex::new(0.0, 0.0); length / 2 + 1],
+            time: vec![0.0; block * 2],
+            scratch: vec![Complex::new(0.0, 0.0); scratch_len],
+            sample_rate,
+        }
+    }
+
+    /// Build the kernels of the enabled `filters` into `kernels`.
+    fn build(&mut self, filters: &[Filter], design: FilterDesign, kernels: &mut [Complex<f32>]) {
+        let length = self.kernel.len();
+
+        for matrix in self.matrix.iter_mut() {
+            *matrix = [1.0, 0.0, 0.0, 1.0];
+        }
+
+        for filter in filters.iter().filter(|filter| *filter.enabled) {
+            let mut state = FilterState::default();
+            state.set_params(filter, design, self.sample_rate);
+
+            for (matrix, &(sin, cos)) in self.matrix.iter_mut().zip(&self.half_angles) {
+                let g = state.magnitude(sin, cos);
+
+                // the band as a matrix acting on left and right
+                let band = match filter.placement {
+                    Placement::Stereo => [g, 0.0, 0.0, g],
+                    Placement::Left => [g, 0.0, 0.0, 1.0],
+                    Placement::Right => [1.0, 0.0, 0.0, g],
+                    Placement::Mid => {
+                        let (a, b) = ((g + 1.0) / 2.0, (g - 1.0) / 2.0);
+                        [a, b, b, a]
+                    }
+                    Placement::Side => {
+                        let (a, b) = ((1.0 + g) / 2.0, (1.0 - g) / 2.0);
+                        [a, b, b, a]
+                    }
+                };
+
+                let [a, b, c, d] = *matrix;
+
+                *matrix = [
+                    band[0] * a + band[1] * c,
+                    band[0] * b + band[1] * d,
+                    band[2] * a + band[3] * c,
+                    band[2] * b + band[3] * d,
+                ];
+            }
+        }
+
+        for entry in 0..4 {
+            for (spectrum, matrix) in self.spectrum.iter_mut().zip(&self.matrix) {
+                *spectrum = Complex::new(matrix[entry], 0.0);
+            }
+
+            self.ifft
+                .process_with_scratch(&mut self.spectrum, &mut self.kernel, &mut self.scratch)
+                .unwrap();
+
+            // the zero phase impulse is centered at 0, rotate it to the
+            // middle of the kernel and window it to tame the truncation
+            self.kernel.rotate_right(length / 2);
+
+            for (i, sample) in self.kernel.iter_mut().enumerate() {
+                let window = 0.5 * (1.0 - f32::cos(2.0 * PI * i as f32 / length as f32));
+                *sample *= window / length as f32;
+            }
+
+            self.partition(entry, kernels);
+        }
+    }
+
+    /// Split the time domain kernel in `self.kernel` into the partition
+    /// spectra of matrix `entry` in `kernels`.
+    fn partition(&mut self, entry: usize, kernels: &mut [Complex<f32>]) {
+        let bins = self.block + 1;
+
+        for partition in 0..self.partitions {
+            let start = partition * self.block;
+
+            self.time[..self.block].copy_from_slice(&self.kernel[start..start + self.block]);
+            self.time[self.block..].fill(0.0);
+
+            let offset = (entry * self.partitions + partition) * bins;
+            let kernel = &mut kernels[offset..offset + bins];
+
+            self.fft
+                .process_with_scratch(&mut self.time, kernel, &mut self.scratch)
+                .unwrap();
+        }
+    }
+}
+
+/// Whether the bands are run as minimum phase filters or as a linear phase
+/// convolution.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum PhaseMode {
+    #[default]
+    Minimum,
+    /// Dynamic bands stay at their static gain, as the kernel is only
+    /// rebuilt when the parameters change.
+    Linear,
+}
+
+impl PhaseMode {
+    pub const MAX_ID: u32 = 1;
+
+    pub fn id(&self) -> u32 {
+        match self {
+            PhaseMode::Minimum => 0,
+            PhaseMode::Linear => 1,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<PhaseMode> {
+        match id {
+            0 => Some(PhaseMode::Minimum),
+            1 => Some(PhaseMode::Linear),
+            _ => None,
+        }
+    }
+}
+
+impl Param for PhaseMode {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = PhaseMode::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Minimum.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match PhaseMode::from_id(plain.round() as u32) {
+            Some(PhaseMode::Minimum) => String::from("Minimum Phase"),
+            Some(PhaseMode::Linear) => String::from("Linear Phase"),
+            None => {
+                println!("PhaseMode::to_string: invalid id: {}", plain);
+                String::from("Minimum Phase")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Minimum Phase" => PhaseMode::Minimum.get(),
+            "Linear Phase" => PhaseMode::Linear.get(),
+            _ => PhaseMode::Minimum.get(),
+        }
+    }
+}
+
+/// Length of the linear phase kernel, longer kernels resolve the low end
+/// better at the cost of latency.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Resolution {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+impl Resolution {
+    pub const MAX_ID: u32 = 2;
+
+    /// Block size of the convolution.
+    pub fn block(&self) -> usize {
+        match self {
+            Resolution::Low => 512,
+            Resolution::Medium => 1024,
+            Resolution::High => 2048,
+        }
+    }
+
+    /// Number of blocks the kernel is split into.
+    pub fn partitions(&self) -> usize {
+        match self {
+            Resolution::Low => 4,
+            Resolution::Medium => 8,
+            Resolution::High => 16,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Resolution::Low => 0,
+            Resolution::Medium => 1,
+            Resolution::High => 2,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Resolution> {
+        match id {
+            0 => Some(Resolution::Low),
+            1 => Some(Resolution::Medium),
+            2 => Some(Resolution::High),
+            _ => None,
+        }
+    }
+}
+
+impl Param for Resolution {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Resolution::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::Medium.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Resolution::from_id(plain.round() as u32) {
+            Some(Resolution::Low) => String::from("Low"),
+            Some(Resolution::Medium) => String::from("Medium"),
+            Some(Resolution::High) => String::from("High"),
+            None => {
+                println!("Resolution::to_string: invalid id: {}", plain);
+                String::from("Medium")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "Low" => Resolution::Low.get(),
+            "Medium" => Resolution::Medium.get(),
+            "High" => Resolution::High.get(),
+            _ => Resolution::Medium.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Pseudo random samples in `-0.5..0.5`.
+    fn noise(seed: &mut u32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|_| {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 17;
+                *seed ^= *seed << 5;
+
+                *seed as f32 / u32::MAX as f32 - 0.5
+            })
+            .collect()
+    }
+
+    /// Four random kernels of `length` taps, one per matrix entry, and their
+    /// partition spectra.
+    fn kernels(builder: &mut KernelBuilder, seed: &mut u32) -> (Vec<Vec<f32>>, Vec<Complex<f32>>) {
+        let length = builder.kernel.len();
+        let bins = builder.block + 1;
+
+        let mut spectra = vec![Complex::new(0.0, 0.0); 4 * builder.partitions * bins];
+        let mut taps = Vec::new();
+
+        for entry in 0..4 {
+            let kernel: Vec<f32> = noise(seed, length)
+                .into_iter()
+                .map(|tap| tap / (length as f32).sqrt())
+                .collect();
+
+            builder.kernel.copy_from_slice(&kernel);
+            builder.partition(entry, &mut spectra);
+
+            taps.push(kernel);
+        }
+
+        (taps, spectra)
+    }
+
+    /// Sample `n` of `row` of the direct convolution of `input` with the
+    /// kernels `taps`, delayed by `delay`.
+    fn direct(taps: &[Vec<f32>], input: &[[f32; 2]], row: usize, n: usize, delay: usize) -> f32 {
+        let mut sum = 0.0;
+
+        for column in 0..2 {
+            let kernel = &taps[row * 2 + column];
+
+            for (k, tap) in kernel.iter().enumerate() {
+                if let Some(i) = n.checked_sub(delay + k) {
+                    sum += tap * input[i][column];
+                }
+            }
+        }
+
+        sum
+    }
+
+    #[test]
+    fn overlap_save_matches_direct_convolution() {
+        let resolution = Resolution::Low;
+        let block = resolution.block();
+
+        let mut seed = 0x2545_f491;
+        let mut builder = KernelBuilder::new(resolution, 48000.0);
+        let (old_taps, old_kernels) = kernels(&mut builder, &mut seed);
+        let (new_taps, new_kernels) = kernels(&mut builder, &mut seed);
+
+        let mut linear_phase = LinearPhase::new(resolution, 0, 48000.0);
+        linear_phase.kernels.copy_from_slice(&old_kernels);
+
+        // stand in for the designer, so the swap happens at a known block
+        let (mut built, reader) = triple_buffer(old_kernels.clone());
+        linear_phase.built = reader;
+
+        let left = noise(&mut seed, 12 * block);
+        let right = noise(&mut seed, 12 * block);
+        let input: Vec<[f32; 2]> = left.into_iter().zip(right).map(|(l, r)| [l, r]).collect();
+
+        // published before this sample, the block after it fades over
+        let swap = 6 * block;
+        let fade = swap + block..swap + 2 * block;
+
+        for (n, &frame) in input.iter().enumerate() {
+            if n == swap {
+                built.slot().copy_from_slice(&new_kernels);
+                built.publish();
+            }
+
+            let mut output = frame;
+            linear_phase.process(&mut output);
+
+            for (row, &sample) in output.iter().enumerate() {
+                let old = direct(&old_taps, &input, row, n, block);
+                let new = direct(&new_taps, &input, row, n, block);
+
+                let expected = match n {
+                    n if n < fade.start => old,
+                    n if fade.contains(&n) => {
+                        let fade = (n - fade.start) as f32 / block as f32;
+                        old * (1.0 - fade) + new * fade
+                    }
+                    _ => new,
+                };
+
+                assert!(
+                    (sample - expected).abs() < 1.0e-4,
+                    "sample {n}, row {row}: {sample} != {expected}",
+                );
+            }
+        }
+    }
+}
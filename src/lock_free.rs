@@ -0,0 +1,168 @@
+use std::{
+    cell::UnsafeCell,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// A single producer, single consumer ring of `Copy` items.
+struct Ring<T> {
+    slots: Box<[UnsafeCell<T>]>,
+    /// Total items pushed, only written by the producer.
+    head: AtomicUsize,
+    /// Total items popped, only written by the consumer.
+    tail: AtomicUsize,
+}
+
+// the producer only writes slots the consumer is done with, and the other
+// way around, which `head` and `tail` synchronize
+unsafe impl<T: Send> Sync for Ring<T> {}
+
+/// Create a ring holding `capacity` items, rounded up to a power of two.
+pub fn ring<T: Copy + Default>(capacity: usize) -> (Producer<T>, Consumer<T>) {
+    let slots = (0..capacity.next_power_of_two())
+        .map(|_| UnsafeCell::new(T::default()))
+        .collect();
+
+    let ring = Arc::new(Ring {
+        slots,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+
+    let producer = Producer { ring: ring.clone() };
+    let consumer = Consumer { ring };
+
+    (producer, consumer)
+}
+
+pub struct Producer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T: Copy> Producer<T> {
+    /// Push as many of `items` as there's room for, returns how many.
+    pub fn push(&mut self, items: &[T]) -> usize {
+        let ring = &self.ring;
+        let mask = ring.slots.len() - 1;
+
+        let head = ring.head.load(Ordering::Relaxed);
+        let tail = ring.tail.load(Ordering::Acquire);
+
+        let free = ring.slots.len() - head.wrapping_sub(tail);
+        let count = usize::min(items.len(), free);
+
+        for (i, item) in items[..count].iter().enumerate() {
+            let slot = &ring.slots[head.wrapping_add(i) & mask];
+            unsafe { *slot.get() = *item };
+        }
+
+        ring.head.store(head.wrapping_add(count), Ordering::Release);
+
+        count
+    }
+}
+
+pub struct Consumer<T> {
+    ring: Arc<Ring<T>>,
+}
+
+impl<T: Copy> Consumer<T> {
+    /// Pop as many items as are available into `items`, returns how many.
+    pub fn pop(&mut self, items: &mut [T]) -> usize {
+        let ring = &self.ring;
+        let mask = ring.slots.len() - 1;
+
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+
+        let available = head.wrapping_sub(tail);
+        let count = usize::min(items.len(), available);
+
+        for (i, item) in items[..count].iter_mut().enumerate() {
+            let slot = &ring.slots[tail.wrapping_add(i) & mask];
+            *item = unsafe { *slot.get() };
+        }
+
+        ring.tail.store(tail.wrapping_add(count), Ordering::Release);
+
+        count
+    }
+}
+
+/// Three slots of `T`, so a writer can publish values a reader picks up
+/// without either of them waiting.
+struct TripleBuffer<T> {
+    slots: [UnsafeCell<T>; 3],
+    /// Index of the slot between the writer and the reader, with `DIRTY` set
+    /// if it holds a value the reader hasn't seen.
+    back: AtomicUsize,
+}
+
+// the writer and the reader each own one slot, and only trade them through
+// `back`
+unsafe impl<T: Send> Sync for TripleBuffer<T> {}
+
+const DIRTY: usize = 4;
+
+pub fn triple_buffer<T: Clone>(initial: T) -> (Writer<T>, Reader<T>) {
+    let buffer = Arc::new(TripleBuffer {
+        slots: [
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial.clone()),
+            UnsafeCell::new(initial),
+        ],
+        back: AtomicUsize::new(2),
+    });
+
+    let writer = Writer {
+        buffer: buffer.clone(),
+        index: 0,
+    };
+
+    let reader = Reader { buffer, index: 1 };
+
+    (writer, reader)
+}
+
+pub struct Writer<T> {
+    buffer: Arc<TripleBuffer<T>>,
+    index: usize,
+}
+
+impl<T> Writer<T> {
+    /// The slot to write the next value into, it holds an older value.
+    pub fn slot(&mut self) -> &mut T {
+        unsafe { &mut *self.buffer.slots[self.index].get() }
+    }
+
+    /// Hand the slot to the reader.
+    pub fn publish(&mut self) {
+        let back = self.buffer.back.swap(self.index | DIRTY, Ordering::AcqRel);
+        self.index = back & !DIRTY;
+    }
+}
+
+pub struct Reader<T> {
+    buffer: Arc<TripleBuffer<T>>,
+    index: usize,
+}
+
+impl<T> Reader<T> {
+    /// Pick up the latest published value, returns `true` if there was one.
+    pub fn update(&mut self) -> bool {
+        if self.buffer.back.load(Ordering::Relaxed) & DIRTY == 0 {
+            return false;
+        }
+
+        let back = self.buffer.back.swap(self.index, Ordering::AcqRel);
+        self.index = back & !DIRTY;
+
+        true
+    }
+
+    pub fn get(&self) -> &T {
+        unsafe { &*self.buffer.slots[self.index].get() }
+    }
+}
@@ -0,0 +1,317 @@
+use crate::{
+    analyzer::Analyzer,
+    filter::{Filter, FilterDesign, FilterKind},
+};
+
+/// What is being captured.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capture {
+    Reference,
+    Source,
+}
+
+/// A long term average spectrum.
+#[derive(Default)]
+struct Average {
+    power: Vec<f32>,
+    frames: u32,
+}
+
+impl Average {
+    fn clear(&mut self) {
+        self.power.clear();
+        self.frames = 0;
+    }
+
+    fn add(&mut self, magnitudes: &[f32]) {
+        self.power.resize(magnitudes.len(), 0.0);
+
+        for (power, magnitude) in self.power.iter_mut().zip(magnitudes) {
+            *power += magnitude * magnitude;
+        }
+
+        self.frames += 1;
+    }
+}
+
+/// Captures a reference and a source spectrum and fits the bands to turn one
+/// into the other.
+pub struct MatchEq {
+    pub capturing: Option<Capture>,
+    analyzer: Analyzer,
+    reference: Average,
+    source: Average,
+}
+
+impl Default for MatchEq {
+    fn default() -> Self {
+        Self {
+            capturing: None,
+            analyzer: Analyzer::new(Self::FFT_SIZE),
+            reference: Average::default(),
+            source: Average::default(),
+        }
+    }
+}
+
+impl MatchEq {
+    /// Size of the FFT the spectra are captured with, fixed so captures stay
+    /// comparable when the analyzer settings change.
+    const FFT_SIZE: usize = 4096;
+
+    /// Resolution of the curve the bands are fitted to.
+    const POINTS: usize = 256;
+
+    /// Frames to average before a spectrum is usable, about a second.
+    const MIN_FRAMES: u32 = 16;
+
+    /// Smallest deviation in dB worth placing a band for.
+    const THRESHOLD: f32 = 0.5;
+
+    /// Frequencies the loudness is matched over.
+    const LEVEL_RANGE: (f32, f32) = (50.0, 10000.0);
+
+    /// Fraction of nyquist the fit stops at.
+    const NYQUIST_MARGIN: f32 = 0.9;
+
+    /// Start capturing `capture`, throwing away what was captured before, or
+    /// stop if it's already being captured.
+    pub fn toggle(&mut self, capture: Capture) {
+        if self.capturing == Some(capture) {
+            self.capturing = None;
+            return;
+        }
+
+        self.capturing = Some(capture);
+        self.analyzer.reset();
+
+        match capture {
+            Capture::Reference => self.reference.clear(),
+            Capture::Source => self.source.clear(),
+        }
+    }
+
+    pub fn has_reference(&self) -> bool {
+        self.reference.frames >= Self::MIN_FRAMES
+    }
+
+    pub fn has_source(&self) -> bool {
+        self.source.frames >= Self::MIN_FRAMES
+    }
+
+    /// Feed the input of the plugin, the reference is taken from the
+    /// sidechain instead if it's connected.
+    pub fn capture(&mut self, frames: &[[f32; 2]], channels: usize, sidechain: bool) {
+        let average = match self.capturing {
+            Some(Capture::Reference) if !sidechain => &mut self.reference,
+            Some(Capture::Source) => &mut self.source,
+            _ => return,
+        };
+
+        Self::push(&mut self.analyzer, average, frames, channels);
+    }
+
+    /// Feed the sidechain input.
+    pub fn capture_keys(&mut self, keys: &[[f32; 2]]) {
+        if self.capturing == Some(Capture::Reference) {
+            Self::push(&mut self.analyzer, &mut self.reference, keys, 2);
+        }
+    }
+
+    fn push(analyzer: &mut Analyzer, average: &mut Average, frames: &[[f32; 2]], channels: usize) {
+        for frame in frames {
+            let sample = frame[..channels].iter().sum::<f32>() / channels as f32;
+
+            if analyzer.push(sample) {
+                average.add(analyzer.magnitudes());
+            }
+        }
+    }
+
+    /// Fit the free `filters` to the difference between the reference and
+    /// the source, scaled by `amount` and smoothed over `smoothing` octaves,
+    /// on top of what the other bands already do. Returns `false` if either
+    /// spectrum hasn't been captured.
+    ///
+    /// `sample_rate` is the rate of the input and `filter_rate` the one the
+    /// bands run at.
+    pub fn fit(
+        &self,
+        filters: &mut [Filter],
+        amount: f32,
+        smoothing: f32,
+        design: FilterDesign,
+        sample_rate: f32,
+        filter_rate: f32,
+    ) -> bool {
+        if !self.has_reference() || !self.has_source() {
+            return false;
+        }
+
+        // the captures end at nyquist, which may be below the top band
+        // frequency, and bells can't be placed right at it
+        let max = f32::min(Filter::FREQ_MAX, Self::NYQUIST_MARGIN * sample_rate / 2.0);
+
+        let freqs: Vec<f32> = (0..Self::POINTS)
+            .map(|i| {
+                let frac = i as f32 / (Self::POINTS - 1) as f32;
+                let octaves = f32::log2(max / Filter::FREQ_MIN);
+
+                Filter::FREQ_MIN * f32::powf(2.0, frac * octaves)
+            })
+            .collect();
+
+        let mut target: Vec<f32> = freqs
+            .iter()
+            .map(|&freq| {
+                let reference = self.level(&self.reference, freq, smoothing, sample_rate);
+                let source = self.level(&self.source, freq, smoothing, sample_rate);
+
+                reference - source
+            })
+            .collect();
+
+        // the bands should shape the spectrum, not change its loudness
+        let (low, high) = Self::LEVEL_RANGE;
+        let levels: Vec<f32> = freqs
+            .iter()
+            .zip(&target)
+            .filter(|(freq, _)| (low..=high).contains(*freq))
+            .map(|(_, level)| *level)
+            .collect();
+
+        let offset = levels.iter().sum::<f32>() / levels.len() as f32;
+
+        for target in target.iter_mut() {
+            *target = (*target - offset) * amount;
+        }
+
+        Self::fit_bands(filters, &freqs, &target, design, filter_rate);
+
+        true
+    }
+
+    /// Power in dB of `average` over `smoothing` octaves around `freq`.
+    fn level(&self, average: &Average, freq: f32, smoothing: f32, sample_rate: f32) -> f32 {
+        let bin_width = sample_rate / self.analyzer.size() as f32;
+        let half_width = f32::powf(2.0, smoothing / 2.0);
+
+        // dc is left out, and the window is cut off at nyquist
+        let last = average.power.len() - 1;
+        let low = ((freq / half_width / bin_width).floor() as usize).clamp(1, last);
+        let high = ((freq * half_width / bin_width).ceil() as usize).clamp(low, last);
+
+        let bins = &average.power[low..=high];
+        let power = bins.iter().sum::<f32>() / (bins.len() as f32 * average.frames as f32);
+
+        10.0 * f32::log10(power.max(1.0e-12))
+    }
+
+    /// Whether `filter`, the `index`th of `count`, still has the kind, gain
+    /// and frequency `Filter::new` gave it, so fitting can take it. A band
+    /// that's only disabled was still set up by the user.
+    fn is_free(filter: &Filter, index: usize, count: usize) -> bool {
+        let default = Filter::new(index as u32, count as u32);
+
+        // the frequency goes through the host's normalized value
+        let freq = (*filter.freq / *default.freq - 1.0).abs() < 1.0e-4;
+
+        filter.kind().id() == default.kind().id()
+            && *filter.gain == *default.gain
+            && freq
+            && !*filter.dynamic
+    }
+
+    /// Place bells in the free bands at the largest deviations of `target`
+    /// one at a time, then refine their gains against each other.
+    ///
+    /// The other bands are left alone and only their response is taken out
+    /// of `target`, so applying never throws away the user's bands.
+    fn fit_bands(
+        filters: &mut [Filter],
+        freqs: &[f32],
+        target: &[f32],
+        design: FilterDesign,
+        sample_rate: f32,
+    ) {
+        let mut residual = target.to_vec();
+        let step = f32::log2(freqs[1] / freqs[0]);
+
+        let response = |filter: &Filter, residual: &mut [f32], sign: f32| {
+            for (residual, &freq) in residual.iter_mut().zip(freqs) {
+                *residual -= sign * filter.gain_at(freq, sample_rate, design);
+            }
+        };
+
+        let count = filters.len();
+
+        for (i, filter) in filters.iter().enumerate() {
+            if *filter.enabled && !Self::is_free(filter, i, count) {
+                response(filter, &mut residual, 1.0);
+            }
+        }
+
+        let mut fitted = Vec::with_capacity(filters.len());
+
+        for (i, filter) in filters.iter_mut().enumerate() {
+            if !Self::is_free(filter, i, count) {
+                continue;
+            }
+
+            let peak = residual
+                .iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()));
+
+            let Some((peak, &gain)) = peak else {
+                break;
+            };
+
+            // the rest of the deviations are smaller still
+            if gain.abs() < Self::THRESHOLD {
+                break;
+            }
+
+            // the width of the deviation, between where it's fallen to half
+            let below_half = |j: &usize| residual[*j] * gain.signum() < gain.abs() / 2.0;
+            let low = (0..peak).rev().find(below_half);
+            let high = (peak..residual.len()).find(below_half);
+
+            let low = low.unwrap_or(0);
+            let high = high.unwrap_or(residual.len() - 1);
+            let bandwidth = f32::max((high - low) as f32 * step, step);
+
+            let bandwidth = f32::powf(2.0, bandwidth);
+            let q = bandwidth.sqrt() / (bandwidth - 1.0);
+
+            // start from a fresh band, so none of the old settings linger
+            *filter = Filter::new(i as u32, count as u32);
+            filter.set_kind(FilterKind::Peak);
+
+            *filter.enabled = true;
+            *filter.freq = freqs[peak].clamp(Filter::FREQ_MIN, Filter::FREQ_MAX);
+            *filter.gain = gain.clamp(Filter::GAIN_MIN, Filter::GAIN_MAX);
+            *filter.q = q.clamp(Filter::Q_MIN, Filter::Q_MAX);
+
+            response(filter, &mut residual, 1.0);
+            fitted.push(i);
+        }
+
+        // the bands overlap, so move every gain to what's left once the
+        // others are in place
+        for _ in 0..4 {
+            for &i in fitted.iter() {
+                let filter = &mut filters[i];
+
+                response(filter, &mut residual, -1.0);
+
+                let freq = *filter.freq;
+                let index = freqs.iter().position(|&f| f >= freq).unwrap_or(0);
+
+                *filter.gain = residual[index].clamp(Filter::GAIN_MIN, Filter::GAIN_MAX);
+
+                response(filter, &mut residual, 1.0);
+            }
+        }
+    }
+}
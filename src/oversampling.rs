@@ -0,0 +1,443 @@
+use std::f32::consts::PI;
+
+use ori_vst::prelude::*;
+
+/// A half band FIR in polyphase form, doubling or halving the sample rate.
+///
+/// Every other tap of a half band filter is zero except the center, so only
+/// the odd taps are stored and the center tap is a plain delay.
+struct HalfBand {
+    /// The odd taps, scaled by two for the zero stuffed input of the upsampler.
+    taps: Vec<f32>,
+    even: Vec<f32>,
+    odd: Vec<f32>,
+}
+
+impl HalfBand {
+    /// A half band with `2 * half_length` odd taps.
+    fn new(half_length: usize) -> Self {
+        let taps_len = 2 * half_length;
+        let center = taps_len as f32 - 1.0;
+
+        let mut taps: Vec<f32> = (0..taps_len)
+            .map(|i| {
+                let n = 2.0 * i as f32 - center;
+
+                let sinc = f32::sin(PI * n / 2.0) / (PI * n);
+                let x = PI * n / (center + 1.0);
+                let window = 0.42 + 0.5 * x.cos() + 0.08 * (2.0 * x).cos();
+
+                2.0 * sinc * window
+            })
+            .collect();
+
+        // make sure the even phase passes dc at unity
+        let sum = taps.iter().sum::<f32>();
+
+        for tap in taps.iter_mut() {
+            *tap /= sum;
+        }
+
+        Self {
+            taps,
+            even: vec![0.0; taps_len],
+            odd: vec![0.0; half_length + 1],
+        }
+    }
+
+    /// Delay of the filter in samples at the higher rate.
+    fn latency(&self) -> usize {
+        self.taps.len() - 1
+    }
+
+    fn push(buffer: &mut [f32], sample: f32) {
+        buffer.copy_within(..buffer.len() - 1, 1);
+        buffer[0] = sample;
+    }
+
+    fn dot(&self) -> f32 {
+        self.taps.iter().zip(&self.even).map(|(a, b)| a * b).sum()
+    }
+
+    fn upsample(&mut self, sample: f32) -> [f32; 2] {
+        Self::push(&mut self.even, sample);
+
+        let center = self.taps.len() / 2 - 1;
+        [self.dot(), self.even[center]]
+    }
+
+    fn downsample(&mut self, samples: [f32; 2]) -> f32 {
+        Self::push(&mut self.even, samples[0]);
+        Self::push(&mut self.odd, samples[1]);
+
+        let center = self.taps.len() / 2;
+        (self.dot() + self.odd[center]) / 2.0
+    }
+}
+
+/// Runs a process at a multiple of the sample rate, with cascaded half band
+/// stages up and down.
+pub struct Oversampler {
+    factor: Oversampling,
+    up: [Vec<HalfBand>; 2],
+    down: [Vec<HalfBand>; 2],
+}
+
+impl Oversampler {
+    pub fn new(factor: Oversampling) -> Self {
+        let stages = || -> Vec<HalfBand> {
+            // later stages only have to reject what's above the original
+            // nyquist, so they get away with far fewer taps
+            (0..factor.stages())
+                .map(|stage| match stage {
+                    0 => HalfBand::new(16),
+                    _ => HalfBand::new(6),
+                })
+                .collect()
+        };
+
+        Self {
+            factor,
+            up: [stages(), stages()],
+            down: [stages(), stages()],
+        }
+    }
+
+    pub fn factor(&self) -> usize {
+        self.factor.factor()
+    }
+
+    /// Delay in samples at the original rate, rounded to the nearest sample.
+    pub fn latency(&self) -> usize {
+        let latency = self.up[0].iter().enumerate().map(|(stage, half_band)| {
+            // up and down both add the delay of the stage, at its rate
+            let rate = 2usize.pow(stage as u32 + 1);
+            2.0 * half_band.latency() as f32 / rate as f32
+        });
+
+        latency.sum::<f32>().round() as usize
+    }
+
+    /// Upsample `frames` into `output`, which gets `factor` frames per frame.
+    pub fn upsample(&mut self, frames: &[[f32; 2]], output: &mut Vec<[f32; 2]>) {
+        let factor = self.factor();
+
+        output.clear();
+
+        for frame in frames {
+            let mut samples = [[0.0; Oversampling::MAX_FACTOR]; 2];
+
+            for (channel, up) in self.up.iter_mut().enumerate() {
+                samples[channel][0] = frame[channel];
+
+                for (stage, half_band) in up.iter_mut().enumerate() {
+                    let len = 1 << stage;
+                    let input = samples[channel];
+
+                    for i in 0..len {
+                        let [a, b] = half_band.upsample(input[i]);
+
+                        samples[channel][2 * i] = a;
+                        samples[channel][2 * i + 1] = b;
+                    }
+                }
+            }
+
+            let [left, right] = samples;
+            output.extend(
+                left[..factor]
+                    .iter()
+                    .zip(&right[..factor])
+                    .map(|(l, r)| [*l, *r]),
+            );
+        }
+    }
+
+    /// Downsample `input` from `upsample` back into `frames`.
+    pub fn downsample(&mut self, input: &[[f32; 2]], frames: &mut [[f32; 2]]) {
+        let factor = self.factor();
+
+        for (frame, high) in frames.iter_mut().zip(input.chunks(factor)) {
+            for (channel, down) in self.down.iter_mut().enumerate() {
+                let mut samples = [0.0; Oversampling::MAX_FACTOR];
+
+                for (sample, high) in samples.iter_mut().zip(high) {
+                    *sample = high[channel];
+                }
+
+                for (stage, half_band) in down.iter_mut().enumerate().rev() {
+                    let len = 1 << stage;
+
+                    for i in 0..len {
+                        let pair = [samples[2 * i], samples[2 * i + 1]];
+                        samples[i] = half_band.downsample(pair);
+                    }
+                }
+
+                frame[channel] = samples[0];
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Oversampling {
+    #[default]
+    X1,
+    X2,
+    X4,
+    X8,
+}
+
+impl Oversampling {
+    pub const MAX_ID: u32 = 3;
+    pub const MAX_FACTOR: usize = 8;
+
+    pub fn factor(&self) -> usize {
+        1 << self.stages()
+    }
+
+    pub fn stages(&self) -> usize {
+        self.id() as usize
+    }
+
+    pub fn id(&self) -> u32 {
+        match self {
+            Oversampling::X1 => 0,
+            Oversampling::X2 => 1,
+            Oversampling::X4 => 2,
+            Oversampling::X8 => 3,
+        }
+    }
+
+    pub fn from_id(id: u32) -> Option<Oversampling> {
+        match id {
+            0 => Some(Oversampling::X1),
+            1 => Some(Oversampling::X2),
+            2 => Some(Oversampling::X4),
+            3 => Some(Oversampling::X8),
+            _ => None,
+        }
+    }
+}
+
+impl Param for Oversampling {
+    fn get(&self) -> f32 {
+        self.id() as f32
+    }
+
+    fn set(&mut self, plain: f32) {
+        *self = Oversampling::from_id(plain.round() as u32).unwrap_or_default();
+    }
+
+    fn default(&self) -> f32 {
+        Self::X1.get()
+    }
+
+    fn plain(&self, normalized: f32) -> f32 {
+        normalized * Self::MAX_ID as f32
+    }
+
+    fn normalize(&self, plain: f32) -> f32 {
+        plain / Self::MAX_ID as f32
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Custom(String::new())
+    }
+
+    fn steps(&self) -> Option<i32> {
+        Some(Self::MAX_ID as i32)
+    }
+
+    fn flags(&self) -> ParamFlags {
+        ParamFlags::LIST
+    }
+
+    fn to_string(&self, plain: f32) -> String {
+        match Oversampling::from_id(plain.round() as u32) {
+            Some(oversampling) => format!("{}x", oversampling.factor()),
+            None => {
+                println!("Oversampling::to_string: invalid id: {}", plain);
+                String::from("1x")
+            }
+        }
+    }
+
+    fn from_string(&self, string: &str) -> f32 {
+        match string {
+            "1x" => Oversampling::X1.get(),
+            "2x" => Oversampling::X2.get(),
+            "4x" => Oversampling::X4.get(),
+            "8x" => Oversampling::X8.get(),
+            _ => Oversampling::X1.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The complex amplitude of the component at `w` radians per sample,
+    /// measured with a hann window to keep the other components out.
+    fn measure(samples: &[f32], w: f64) -> (f64, f64) {
+        let len = samples.len() as f64;
+        let (mut re, mut im, mut weight) = (0.0, 0.0, 0.0);
+
+        for (n, sample) in samples.iter().enumerate() {
+            let window = 0.5 - 0.5 * f64::cos(2.0 * std::f64::consts::PI * n as f64 / len);
+            let phase = w * n as f64;
+
+            re += window * *sample as f64 * phase.cos();
+            im -= window * *sample as f64 * phase.sin();
+            weight += window;
+        }
+
+        (2.0 * f64::hypot(re, im) / weight, f64::atan2(im, re))
+    }
+
+    fn db(gain: f64) -> f64 {
+        20.0 * gain.log10()
+    }
+
+    /// A sine at `freq` times the sample rate, in both channels.
+    fn sine(freq: f64, len: usize) -> Vec<[f32; 2]> {
+        let w = 2.0 * std::f64::consts::PI * freq;
+
+        (0..len)
+            .map(|n| [f64::sin(w * n as f64) as f32; 2])
+            .collect()
+    }
+
+    const OVERSAMPLINGS: [Oversampling; 3] = [Oversampling::X2, Oversampling::X4, Oversampling::X8];
+
+    #[test]
+    fn half_band_taps_are_symmetric_and_pass_dc() {
+        for half_length in [6, 16] {
+            let half_band = HalfBand::new(half_length);
+            let taps = &half_band.taps;
+
+            for (a, b) in taps.iter().zip(taps.iter().rev()) {
+                assert!((a - b).abs() < 1.0e-7, "{a} != {b}");
+            }
+
+            // the even phase alone passes dc at unity, as does the center tap
+            // of the odd phase
+            let sum = taps.iter().sum::<f32>();
+            assert!((sum - 1.0).abs() < 1.0e-6, "{sum}");
+
+            // the center of the full filter, at the higher rate
+            assert_eq!(half_band.latency(), 2 * half_length - 1);
+        }
+    }
+
+    #[test]
+    fn upsampling_passes_the_pass_band_and_rejects_the_images() {
+        let len = 8192;
+
+        for oversampling in OVERSAMPLINGS {
+            let factor = oversampling.factor();
+
+            // up to 17.6 kHz at 44.1 kHz
+            for freq in [0.01, 0.1, 0.2, 0.3, 0.4] {
+                let mut oversampler = Oversampler::new(oversampling);
+                let mut output = Vec::new();
+                oversampler.upsample(&sine(freq, len), &mut output);
+
+                let output: Vec<f32> = output[output.len() / 2..]
+                    .iter()
+                    .map(|frame| frame[0])
+                    .collect();
+
+                let w = |freq: f64| 2.0 * std::f64::consts::PI * freq / factor as f64;
+
+                let (gain, _) = measure(&output, w(freq));
+                assert!(db(gain).abs() < 0.1, "{factor}x at {freq}: {} dB", db(gain));
+
+                // the images around every multiple of the original rate
+                for k in 1..factor {
+                    for image in [k as f64 - freq, k as f64 + freq] {
+                        if image >= factor as f64 / 2.0 {
+                            continue;
+                        }
+
+                        let (gain, _) = measure(&output, w(image));
+                        assert!(
+                            db(gain) < -60.0,
+                            "{factor}x at {freq}, image at {image}: {} dB",
+                            db(gain),
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn downsampling_rejects_above_the_original_nyquist() {
+        let len = 8192;
+
+        for oversampling in OVERSAMPLINGS {
+            let factor = oversampling.factor();
+
+            // components above the original nyquist that would alias into
+            // the audible range
+            for freq in [0.6, 0.7, 0.9, 1.4, 2.3] {
+                if freq >= factor as f64 / 2.0 {
+                    continue;
+                }
+
+                let mut oversampler = Oversampler::new(oversampling);
+                let input = sine(freq / factor as f64, len * factor);
+                let mut output = vec![[0.0; 2]; len];
+                oversampler.downsample(&input, &mut output);
+
+                let output: Vec<f32> = output[len / 2..].iter().map(|frame| frame[0]).collect();
+
+                // where it would alias to
+                let alias = freq - freq.round();
+                let w = 2.0 * std::f64::consts::PI * alias.abs();
+
+                let (gain, _) = measure(&output, w);
+                assert!(db(gain) < -60.0, "{factor}x at {freq}: {} dB", db(gain));
+            }
+        }
+    }
+
+    #[test]
+    fn latency_is_the_round_trip_delay_rounded() {
+        let len = 16384;
+        let freq = 0.001;
+
+        for oversampling in OVERSAMPLINGS {
+            let mut oversampler = Oversampler::new(oversampling);
+
+            let input = sine(freq, len);
+            let mut high = Vec::new();
+            let mut output = vec![[0.0; 2]; len];
+            oversampler.upsample(&input, &mut high);
+            oversampler.downsample(&high, &mut output);
+
+            let output: Vec<f32> = output.iter().map(|frame| frame[0]).collect();
+            let input: Vec<f32> = input.iter().map(|frame| frame[0]).collect();
+
+            let w = 2.0 * std::f64::consts::PI * freq;
+            let (_, phase_in) = measure(&input[len / 2..], w);
+            let (_, phase_out) = measure(&output[len / 2..], w);
+
+            let delay = (phase_in - phase_out).rem_euclid(2.0 * std::f64::consts::PI) / w;
+
+            // half a sample either way, a delay of half a sample more than
+            // a whole one can round to either side
+            let error = delay - oversampler.latency() as f64;
+
+            assert!(
+                error.abs() <= 0.51,
+                "{}x: delay of {delay}, latency of {}",
+                oversampling.factor(),
+                oversampler.latency(),
+            );
+        }
+    }
+}